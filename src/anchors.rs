@@ -0,0 +1,257 @@
+//! Intra-document anchors and references, so repeated data only has to be written once.
+//!
+//! A value annotated with `anchor::name` can be referred to anywhere in the same document
+//! (including earlier) with `ref::name`:
+//!
+//! ```ion
+//! {
+//!   frames: {
+//!     idle: anchor::walk_frame::{ duration: 100, sprite: "walk_0.png" },
+//!     walk: [ref::walk_frame, ref::walk_frame, { duration: 50, sprite: "walk_1.png" }],
+//!   }
+//! }
+//! ```
+//!
+//! The anchor annotations are removed from the resolved value, and a reference is replaced by the
+//! anchored value along with its annotations (the reference's own annotations are dropped).
+
+use std::collections::HashMap;
+use std::rc::Rc;
+use crate::error::{IonError, IonErrorType, IonResult};
+use crate::path::IonPath;
+use crate::types::{Annotations, IonList, IonStruct, IonValue, Symbol};
+
+#[derive(Debug, Clone)]
+/// Annotations used to mark anchors and references.
+pub struct AnchorOptions {
+    pub anchor_annotation: String,
+    pub ref_annotation: String,
+}
+
+impl Default for AnchorOptions {
+    fn default() -> Self {
+        AnchorOptions {
+            anchor_annotation: "anchor".to_string(),
+            ref_annotation: "ref".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// A resolved value in which every reference to the same anchor shares one `Rc`.
+pub enum IonNode {
+    /// Any value other than a list or struct.
+    Scalar(IonValue),
+    List(Vec<Rc<IonNode>>, Annotations),
+    Struct(HashMap<Symbol, Rc<IonNode>>, Annotations),
+}
+
+impl IonNode {
+    /// Returns a reference to this node's annotations.
+    pub fn annotations(&self) -> &Annotations {
+        match self {
+            IonNode::Scalar(value) => value.annotations(),
+            IonNode::List(_, ann) | IonNode::Struct(_, ann) => ann,
+        }
+    }
+
+    /// Expands this node into a plain `IonValue`, copying shared values wherever they're used.
+    pub fn to_value(&self) -> IonValue {
+        match self {
+            IonNode::Scalar(value) => value.clone(),
+            IonNode::List(items, ann) => {
+                let items = items.iter().map(|item| item.to_value()).collect();
+                IonValue::List(IonList { items }, ann.clone())
+            }
+            IonNode::Struct(fields, ann) => {
+                let fields = fields.iter().map(|(name, node)| (name.clone(), node.to_value())).collect();
+                IonValue::Struct(IonStruct::new(fields), ann.clone())
+            }
+        }
+    }
+}
+
+/// Resolves every reference in `doc`, returning the expanded tree.
+pub fn resolve_refs(doc: &IonValue, options: &AnchorOptions) -> IonResult<IonValue> {
+    Ok(resolve_refs_shared(doc, options)?.to_value())
+}
+
+/// Resolves every reference in `doc`, returning a graph where each anchored value is only
+/// stored once.
+pub fn resolve_refs_shared(doc: &IonValue, options: &AnchorOptions) -> IonResult<Rc<IonNode>> {
+    let mut resolver = Resolver { options, anchors: HashMap::new(), resolved: HashMap::new(), expanding: Vec::new() };
+    resolver.collect(doc, &IonPath::root())?;
+    resolver.node(doc, &IonPath::root())
+}
+
+fn reference_error(message: String, path: &IonPath) -> IonError {
+    IonError::new(IonErrorType::ReferenceError(message), path.to_scopes())
+}
+
+struct Resolver<'d, 'o> {
+    options: &'o AnchorOptions,
+    /// Every anchor in the document, with where it's defined.
+    anchors: HashMap<String, (IonPath, &'d IonValue)>,
+    resolved: HashMap<String, Rc<IonNode>>,
+    /// Anchors currently being resolved, for cycle detection.
+    expanding: Vec<String>,
+}
+
+impl<'d, 'o> Resolver<'d, 'o> {
+    /// Returns the anchor name of `value` and its annotations without the anchor,
+    /// or `None` if it isn't anchored.
+    fn anchor_of(&self, value: &IonValue, path: &IonPath) -> IonResult<Option<(String, Annotations)>> {
+        let ann = value.annotations();
+        match ann.iter().position(|a| *a == self.options.anchor_annotation) {
+            Some(i) => match ann.get(i + 1) {
+                Some(name) => {
+                    let mut rest = ann.clone();
+                    rest.remove(i);
+                    rest.remove(i);
+                    Ok(Some((name.to_string(), rest)))
+                }
+                None => Err(reference_error(format!("`{}` must be followed by the anchor's name", ann[i]), path)),
+            },
+            None => Ok(None),
+        }
+    }
+
+    fn collect(&mut self, value: &'d IonValue, path: &IonPath) -> IonResult<()> {
+        if let Some((name, _)) = self.anchor_of(value, path)? {
+            if let Some((first, _)) = self.anchors.get(&name) {
+                return Err(reference_error(format!("anchor `{}` is already defined at {}", name, first), path));
+            }
+            self.anchors.insert(name, (path.clone(), value));
+        }
+        match value {
+            IonValue::List(list, _) => {
+                for (i, item) in list.iter().enumerate() {
+                    self.collect(item, &path.index(i))?;
+                }
+            }
+            IonValue::Struct(st, _) => {
+                for (name, field) in st.iter_fields() {
+                    self.collect(field, &path.field(name))?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn node(&mut self, value: &'d IonValue, path: &IonPath) -> IonResult<Rc<IonNode>> {
+        if value.has_annotation(&self.options.ref_annotation) {
+            return match value.as_text() {
+                Some(name) => self.anchored(name, path),
+                None => Err(reference_error(format!("expected an anchor name, found {}", value), path)),
+            };
+        }
+        match self.anchor_of(value, path)? {
+            Some((name, _)) => self.anchored(&name, path),
+            None => self.build(value, value.annotations().clone(), path),
+        }
+    }
+
+    /// Returns the shared node for the anchor `name`, resolving it the first time it's used.
+    fn anchored(&mut self, name: &str, path: &IonPath) -> IonResult<Rc<IonNode>> {
+        if let Some(node) = self.resolved.get(name) {
+            return Ok(node.clone());
+        }
+        if self.expanding.iter().any(|n| n == name) {
+            let mut cycle = self.expanding.clone();
+            cycle.push(name.to_string());
+            return Err(reference_error(format!("reference cycle: {}", cycle.join(" -> ")), path));
+        }
+        let (def_path, def) = match self.anchors.get(name) {
+            Some((def_path, def)) => (def_path.clone(), *def),
+            None => return Err(reference_error(format!("unknown anchor `{}`", name), path)),
+        };
+        let (_, annotations) = self.anchor_of(def, &def_path)?.expect("anchors are only collected from anchored values");
+        self.expanding.push(name.to_string());
+        let node = self.build(def, annotations, &def_path);
+        self.expanding.pop();
+        let node = node?;
+        self.resolved.insert(name.to_string(), node.clone());
+        Ok(node)
+    }
+
+    fn build(&mut self, value: &'d IonValue, annotations: Annotations, path: &IonPath) -> IonResult<Rc<IonNode>> {
+        let node = match value {
+            IonValue::List(list, _) => {
+                let mut items = Vec::with_capacity(list.len());
+                for (i, item) in list.iter().enumerate() {
+                    items.push(self.node(item, &path.index(i))?);
+                }
+                IonNode::List(items, annotations)
+            }
+            IonValue::Struct(st, _) => {
+                let mut fields = HashMap::new();
+                for (name, field) in st.iter_fields() {
+                    fields.insert(name.clone(), self.node(field, &path.field(name))?);
+                }
+                IonNode::Struct(fields, annotations)
+            }
+            scalar => {
+                let mut scalar = scalar.clone();
+                *scalar.annotations_mut() = annotations;
+                IonNode::Scalar(scalar)
+            }
+        };
+        Ok(Rc::new(node))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use super::*;
+    use crate::test_util::value;
+
+    #[test]
+    fn expands_references() {
+        let doc = value("{ walk: [ref::frame, ref::frame, { duration: 50 }], idle: anchor::frame::still::{ duration: 100 } }");
+        let resolved = resolve_refs(&doc, &AnchorOptions::default()).unwrap();
+        let frame = value("still::{ duration: 100 }");
+        let expected = IonValue::Struct(IonStruct::new(vec![
+            (Symbol::new("walk"), value("[still::{ duration: 100 }, still::{ duration: 100 }, { duration: 50 }]")),
+            (Symbol::new("idle"), frame),
+        ].into_iter().collect()), Annotations::new());
+        assert_eq!(resolved, expected);
+    }
+
+    #[test]
+    fn shares_anchored_values() {
+        let doc = value("[anchor::a::{ x: 1 }, ref::a, [ref::a]]");
+        let node = resolve_refs_shared(&doc, &AnchorOptions::default()).unwrap();
+        let items = match &*node {
+            IonNode::List(items, _) => items,
+            other => panic!("expected a list, got {:?}", other),
+        };
+        assert!(Rc::ptr_eq(&items[0], &items[1]));
+        match &*items[2] {
+            IonNode::List(inner, _) => assert!(Rc::ptr_eq(&items[0], &inner[0])),
+            other => panic!("expected a list, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn uses_custom_annotations() {
+        let options = AnchorOptions { anchor_annotation: "def".to_string(), ref_annotation: "use".to_string() };
+        let resolved = resolve_refs(&value("[def::one::1, use::one]"), &options).unwrap();
+        assert_eq!(resolved, value("[1, 1]"));
+    }
+
+    #[rstest(text, message,
+        case("[ref::missing]", "unknown anchor `missing`"),
+        case("[anchor::a::[ref::b], anchor::b::[ref::a]]", "reference cycle: a -> b -> a"),
+        case("{ x: anchor::a::1, y: anchor::a::2 }", "anchor `a` is already defined at"),
+        case("[anchor::1]", "must be followed by the anchor's name"),
+        case("[ref::1]", "expected an anchor name"),
+    )]
+    fn reports_reference_errors(text: &str, message: &str) {
+        match resolve_refs(&value(text), &AnchorOptions::default()) {
+            Err(IonError { ty: IonErrorType::ReferenceError(msg), .. }) => assert!(msg.contains(message), "{}", msg),
+            other => panic!("expected a reference error, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,362 @@
+//! Read-only values which borrow their text from the buffer they were read from.
+//!
+//! `IonReader::read_borrowed` avoids copying strings and blobs out of binary Ion input by pointing
+//! into the buffer where they're stored. Ion C decodes text Ion into memory of its own, so values
+//! read from text, and symbols (which binary Ion stores as IDs), are copied as usual.
+//!
+//! Types implementing `IonDeserializeRef` can then keep `&'a str`s pointing into the document
+//! instead of allocating `String`s:
+//!
+//! ```ignore
+//! struct Frame<'a> { sprite: &'a str, duration: i64 }
+//!
+//! impl<'a> IonDeserializeRef<'a> for Frame<'a> {
+//!     fn deserialize_ref(walker: &IonRefWalker<'_, 'a>) -> IonResult<Self> {
+//!         Ok(Frame { sprite: walker.get_string("sprite")?, duration: walker.get_integer("duration")? })
+//!     }
+//! }
+//!
+//! let values = IonReader::read_borrowed(&bytes)?;
+//! let frames: Vec<Frame> = IonRefWalker::new(&values[0]).as_typed_list()?;
+//! ```
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use bytes::Bytes;
+use chrono::{DateTime, FixedOffset};
+use paste::paste;
+use crate::error::{IonError, IonErrorType, IonResult};
+use crate::traits::IonDeserializeRef;
+use crate::types::{Annotations, IonList, IonStruct, IonType, IonValue};
+use crate::symbol::Symbol;
+use crate::walker::{type_fns, Scope};
+
+#[derive(Debug, Clone, PartialEq)]
+/// An Ion value whose strings and symbols may borrow from the buffer it was read from, or from
+/// the `IonValue` it was converted from. Only strings read from binary Ion borrow from the buffer.
+pub enum IonValueRef<'a> {
+    Null(Annotations),
+    Boolean(bool, Annotations),
+    Integer(i64, Annotations),
+    Float(f64, Annotations),
+    Timestamp(DateTime<FixedOffset>, Annotations),
+    String(Cow<'a, str>, Annotations),
+    Symbol(Cow<'a, str>, Annotations),
+    Blob(Cow<'a, [u8]>, Annotations),
+    List(Vec<IonValueRef<'a>>, Annotations),
+    Struct(HashMap<Symbol, IonValueRef<'a>>, Annotations),
+}
+
+impl<'a> IonValueRef<'a> {
+    /// Returns the type of this value.
+    pub fn ty(&self) -> IonType {
+        match self {
+            IonValueRef::Null(_) => IonType::Null,
+            IonValueRef::Boolean(_,_) => IonType::Boolean,
+            IonValueRef::Integer(_,_) => IonType::Integer,
+            IonValueRef::Float(_,_) => IonType::Float,
+            IonValueRef::Timestamp(_,_) => IonType::Timestamp,
+            IonValueRef::String(_,_) => IonType::String,
+            IonValueRef::Symbol(_,_) => IonType::Symbol,
+            IonValueRef::Blob(_,_) => IonType::Blob,
+            IonValueRef::List(_,_) => IonType::List,
+            IonValueRef::Struct(_,_) => IonType::Struct,
+        }
+    }
+
+    /// Returns a reference to this value's annotations.
+    pub fn annotations(&self) -> &Annotations {
+        match self {
+            IonValueRef::Null(ann) => ann,
+            IonValueRef::Boolean(_, ann) | IonValueRef::Integer(_, ann) | IonValueRef::Float(_, ann)
+            | IonValueRef::Timestamp(_, ann) | IonValueRef::String(_, ann) | IonValueRef::Symbol(_, ann)
+            | IonValueRef::Blob(_, ann) | IonValueRef::List(_, ann) | IonValueRef::Struct(_, ann) => ann,
+        }
+    }
+
+    /// Returns the text of a string or symbol.
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            IonValueRef::String(s, _) | IonValueRef::Symbol(s, _) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Returns the named field, if this is a struct which has it.
+    pub fn field(&self, name: &str) -> Option<&IonValueRef<'a>> {
+        match self {
+            IonValueRef::Struct(fields, _) => fields.get(name),
+            _ => None,
+        }
+    }
+
+    /// Copies this value into an owned `IonValue`.
+    pub fn to_value(&self) -> IonValue {
+        match self {
+            IonValueRef::Null(ann) => IonValue::Null(ann.clone()),
+            IonValueRef::Boolean(b, ann) => IonValue::Boolean(*b, ann.clone()),
+            IonValueRef::Integer(i, ann) => IonValue::Integer(*i, ann.clone()),
+            IonValueRef::Float(f, ann) => IonValue::Float(*f, ann.clone()),
+            IonValueRef::Timestamp(t, ann) => IonValue::Timestamp(*t, ann.clone()),
+            IonValueRef::String(s, ann) => IonValue::String(s.to_string(), ann.clone()),
+            IonValueRef::Symbol(s, ann) => IonValue::Symbol(Symbol::new(s), ann.clone()),
+            IonValueRef::Blob(b, ann) => IonValue::Blob(Bytes::from(&b[..]), ann.clone()),
+            IonValueRef::List(items, ann) => {
+                let items = items.iter().map(IonValueRef::to_value).collect();
+                IonValue::List(IonList { items }, ann.clone())
+            }
+            IonValueRef::Struct(fields, ann) => {
+                let fields = fields.iter().map(|(name, value)| (name.clone(), value.to_value())).collect();
+                IonValue::Struct(IonStruct::new(fields), ann.clone())
+            }
+        }
+    }
+}
+
+impl<'a> From<&'a IonValue> for IonValueRef<'a> {
+    /// Borrows every string and blob from an owned value.
+    fn from(value: &'a IonValue) -> Self {
+        match value {
+            IonValue::Null(ann) => IonValueRef::Null(ann.clone()),
+            IonValue::Boolean(b, ann) => IonValueRef::Boolean(*b, ann.clone()),
+            IonValue::Integer(i, ann) => IonValueRef::Integer(*i, ann.clone()),
+            IonValue::Float(f, ann) => IonValueRef::Float(*f, ann.clone()),
+            IonValue::Timestamp(t, ann) => IonValueRef::Timestamp(*t, ann.clone()),
+            IonValue::String(s, ann) => IonValueRef::String(Cow::Borrowed(s), ann.clone()),
+            IonValue::Symbol(s, ann) => IonValueRef::Symbol(Cow::Borrowed(s), ann.clone()),
+            IonValue::Blob(b, ann) => IonValueRef::Blob(Cow::Borrowed(&b[..]), ann.clone()),
+            IonValue::List(list, ann) => IonValueRef::List(list.iter().map(IonValueRef::from).collect(), ann.clone()),
+            IonValue::Struct(st, ann) => {
+                let fields = st.iter_fields().map(|(name, value)| (name.clone(), IonValueRef::from(value))).collect();
+                IonValueRef::Struct(fields, ann.clone())
+            }
+        }
+    }
+}
+
+/// Walks an `IonValueRef` for `IonDeserializeRef` impls, keeping track of where it is for errors.
+///
+/// Works like `IonWalker`, except that strings, symbols and blobs are handed out with the
+/// lifetime of the value being walked rather than of the walker.
+pub struct IonRefWalker<'w, 'a> {
+    data: &'a IonValueRef<'a>,
+    scope: Scope<'w>,
+    parent: Option<&'w IonRefWalker<'w, 'a>>,
+}
+
+impl<'w, 'a> IonRefWalker<'w, 'a> {
+    /// Construct an IonRefWalker around the given reference, with no scopes.
+    pub fn new(data: &'a IonValueRef<'a>) -> Self {
+        IonRefWalker { data, scope: Scope::Root, parent: None }
+    }
+
+    /// Construct an IonRefWalker around the given reference, with the given scopes.
+    pub fn with_scopes(data: &'a IonValueRef<'a>, scopes: Vec<String>) -> Self {
+        IonRefWalker { data, scope: Scope::Owned(scopes), parent: None }
+    }
+
+    /// Convenience function for deserializing values that are IonDeserializeRef
+    pub fn deserialize<T: IonDeserializeRef<'a>>(data: &'a IonValueRef<'a>) -> IonResult<T> {
+        T::deserialize_ref(&IonRefWalker::new(data))
+    }
+
+    /// Returns the value being walked.
+    pub fn value(&self) -> &'a IonValueRef<'a> { self.data }
+
+    /// Returns this walker's scopes. Allocates, so it's intended for building errors.
+    pub fn clone_scopes(&self) -> Vec<String> {
+        let mut scopes = match self.parent {
+            Some(parent) => parent.clone_scopes(),
+            None => Vec::new(),
+        };
+        self.scope.push_to(&mut scopes);
+        scopes
+    }
+
+    /// Returns this walker's scopes with an extra scope added
+    pub fn clone_scopes_with(&self, scope: impl AsRef<str>) -> Vec<String> {
+        let mut scopes = self.clone_scopes();
+        scopes.push(scope.as_ref().to_string());
+        scopes
+    }
+
+    /// Convenience function to create an IonError with a copy of this walker's scopes
+    pub fn error(&self, error: IonErrorType) -> IonError {
+        IonError::new(error, self.clone_scopes())
+    }
+
+    /// Returns the list of annotations for the current value.
+    pub fn annotations(&self) -> &'a Annotations {
+        self.data.annotations()
+    }
+
+    /// Returns true if the current value has an annotation with the given value.
+    pub fn has_annotation(&self, ann: impl AsRef<str>) -> bool {
+        self.data.annotations().contains(ann.as_ref())
+    }
+
+    type_fns!(IonValueRef: Struct,   (s,_) => Ok(s);             &'a HashMap<Symbol, IonValueRef<'a>>);
+    type_fns!(IonValueRef: List,     (l,_) => Ok(&l[..]);        &'a [IonValueRef<'a>]);
+    type_fns!(IonValueRef: Null,     (_)   => Ok(());            ());
+    type_fns!(IonValueRef: Boolean,  (b,_) => Ok(*b);            bool);
+    type_fns!(IonValueRef: Integer,  (i,_) => Ok(*i);            i64);
+    type_fns!(IonValueRef: String,   (s,_) => Ok(s.as_ref());    &'a str);
+    type_fns!(IonValueRef: Symbol,   (s,_) => Ok(s.as_ref());    &'a str);
+    type_fns!(IonValueRef: Blob,     (b,_) => Ok(b.as_ref());    &'a [u8]);
+    type_fns!(IonValueRef: Timestamp,(t,_) => Ok(t);             &'a DateTime<FixedOffset>);
+
+    /// Attempt to read the current value as a float
+    // Special case for float accepting integers
+    pub fn as_float(&self) -> IonResult<f64> {
+        match self.data {
+            IonValueRef::Float(f, _) => Ok(*f),
+            IonValueRef::Integer(i, _) => Ok(*i as f64),
+            _ => Err(self.error(IonErrorType::WrongType { found: self.data.ty(), expected: IonType::Float })),
+        }
+    }
+
+    /// Attempt to read the named field as a float. Assumes current value is a struct.
+    pub fn get_float(&self, field_name: impl AsRef<str>) -> IonResult<f64> {
+        self.enter_scoped(field_name)?.as_float()
+    }
+
+    /// Generic version of the as_X method that works for any type which is `IonDeserializeRef`.
+    pub fn as_type<T: IonDeserializeRef<'a>>(&self) -> IonResult<T> {
+        T::deserialize_ref(self)
+    }
+
+    /// Generic version of the get_X method that works for any type which is `IonDeserializeRef`.
+    pub fn get_type<T: IonDeserializeRef<'a>>(&self, field_name: impl AsRef<str>) -> IonResult<T> {
+        T::deserialize_ref(&self.enter_scoped(field_name)?)
+    }
+
+    /// Looks up a field of the current struct.
+    fn lookup(&self, name: &str) -> IonResult<Option<(&'a Symbol, &'a IonValueRef<'a>)>> {
+        Ok(self.as_struct()?.get_key_value(name))
+    }
+
+    /// Attempt to move into the named field without assuming its type.
+    /// Assumes current value is a struct.
+    pub fn enter(&self, field_name: impl AsRef<str>) -> IonResult<IonRefWalker<'_, 'a>> {
+        match self.lookup(field_name.as_ref())? {
+            Some((_, data)) => Ok(IonRefWalker { data, scope: Scope::Root, parent: Some(self) }),
+            None => Err(IonError::new(
+                IonErrorType::MissingField(field_name.as_ref().to_string()),
+                self.clone_scopes_with(field_name)
+            ))
+        }
+    }
+
+    /// Like `enter`, but scoped by the field's name, as in `IonWalker`.
+    fn enter_scoped(&self, field_name: impl AsRef<str>) -> IonResult<IonRefWalker<'_, 'a>> {
+        match self.lookup(field_name.as_ref())? {
+            Some((name, data)) => Ok(IonRefWalker { data, scope: Scope::Field(name), parent: Some(self) }),
+            None => Err(IonError::new(
+                IonErrorType::MissingField(field_name.as_ref().to_string()),
+                self.clone_scopes_with(field_name)
+            ))
+        }
+    }
+
+    pub fn as_typed_list<T: IonDeserializeRef<'a>>(&self) -> IonResult<Vec<T>> {
+        let mut result = Vec::new();
+        for (i, data) in self.as_list()?.iter().enumerate() {
+            result.push(T::deserialize_ref(&IonRefWalker { data, scope: Scope::Index(i), parent: Some(self) })?);
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use super::*;
+    use crate::reader::IonReader;
+    use crate::writer::IonWriter;
+
+    fn read(buf: &[u8]) -> IonValueRef<'_> {
+        IonReader::read_borrowed(buf).unwrap().remove(0)
+    }
+
+    fn binary(text: &str) -> Vec<u8> {
+        IonWriter::write_binary(IonReader::read_string(text).unwrap().values()).unwrap()
+    }
+
+    fn is_borrowed(value: &IonValueRef) -> bool {
+        match value {
+            IonValueRef::String(s, _) | IonValueRef::Symbol(s, _) => matches!(s, Cow::Borrowed(_)),
+            IonValueRef::Blob(b, _) => matches!(b, Cow::Borrowed(_)),
+            other => panic!("expected text or a blob, got {:?}", other),
+        }
+    }
+
+    #[rstest(text, borrowed,
+        case(r#""walk_0.png""#, true),
+        case(r#""tab\tseparated""#, true),
+        case(r#"frames::"walk_0.png""#, true),
+        case(r#""a string long enough to need a separate length field""#, true),
+        case("walk_0", false),
+        case("{{aGVsbG8=}}", true),
+        case("{{}}", true),
+        case("png::{{MDEyMzQ1Njc4OWFiY2RlZjAxMjM0NTY3ODlhYmNkZWYwMTIzNDU2Nzg5YWJjZGVm}}", true),
+    )]
+    fn borrows_strings_from_binary(text: &str, borrowed: bool) {
+        let buf = binary(text);
+        let value = read(&buf);
+        assert_eq!(value.to_value(), IonReader::read_string(text).unwrap().into_single().unwrap());
+        assert_eq!(is_borrowed(&value), borrowed);
+        let bytes = match &value {
+            IonValueRef::String(s, _) => Some(s.as_bytes()),
+            IonValueRef::Blob(b, _) => Some(&b[..]),
+            _ => None,
+        };
+        if let Some(bytes) = bytes.filter(|bytes| !bytes.is_empty()) {
+            assert!(buf.as_ptr_range().contains(&bytes.as_ptr()));
+        }
+    }
+
+    #[rstest(text,
+        case(r#""walk_0.png""#),
+        case("walk_0"),
+        case("{{aGVsbG8=}}"),
+    )]
+    fn copies_everything_from_text(text: &str) {
+        assert!(!is_borrowed(&read(text.as_bytes())));
+    }
+
+    #[test]
+    fn borrows_from_owned_values() {
+        let owned = IonReader::read_string(r#"{ sprite: "hero.png", tag: walk, pixels: {{aGVsbG8=}} }"#).unwrap().into_single().unwrap();
+        let value = IonValueRef::from(&owned);
+        let walker = IonRefWalker::new(&value);
+        for name in ["sprite", "tag", "pixels"] {
+            assert!(is_borrowed(walker.enter(name).unwrap().value()), "{}", name);
+        }
+    }
+
+    #[test]
+    fn walks_borrowed_values() {
+        let buf = binary(r#"{ sprite: "hero.png", frames: [{ duration: 1 }, { duration: x }] }"#);
+        let doc = read(&buf);
+        let walker = IonRefWalker::new(&doc);
+        let sprite = walker.get_string("sprite").unwrap();
+        assert_eq!(sprite, "hero.png");
+        assert!(buf.as_ptr_range().contains(&sprite.as_ptr()));
+        let frames = walker.enter("frames").unwrap();
+        assert_eq!(frames.as_list().unwrap().len(), 2);
+        // as with `IonWalker`, `enter` doesn't add a scope but list items and `get_*` fields do
+        assert_eq!(frames.as_typed_list::<Frame>().unwrap_err().scopes, vec!["[1]", "duration"]);
+        assert_eq!(walker.get_integer("sprite").unwrap_err().scopes, vec!["sprite"]);
+    }
+
+    #[derive(Debug)]
+    struct Frame {
+        _duration: i64,
+    }
+
+    impl<'a> IonDeserializeRef<'a> for Frame {
+        fn deserialize_ref(walker: &IonRefWalker<'_, 'a>) -> IonResult<Self> {
+            Ok(Frame { _duration: walker.get_integer("duration")? })
+        }
+    }
+}
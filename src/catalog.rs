@@ -0,0 +1,283 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+use crate::document::IonDocument;
+use crate::error::{IonError, IonErrorType, IonResult};
+use crate::reader::{IonReader, ReadOptions};
+use crate::traits::IonDeserialize;
+use crate::walker::IonWalker;
+
+#[derive(Debug, Clone)]
+/// Options for `IonCatalog::load`.
+pub struct CatalogOptions {
+    /// Only files ending in one of these extensions (without the dot) are loaded.
+    pub extensions: Vec<String>,
+    /// Key each top-level struct by the value of this field instead of keying each file by
+    /// its path. Values without the field are keyed by path as usual.
+    pub id_field: Option<String>,
+    /// Follow symbolic links while walking the directory.
+    pub follow_links: bool,
+    /// Options for reading each file, e.g. to resolve includes.
+    pub read: ReadOptions,
+}
+
+impl Default for CatalogOptions {
+    fn default() -> Self {
+        CatalogOptions {
+            extensions: vec!["ion".to_string()],
+            id_field: None,
+            follow_links: false,
+            read: ReadOptions::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// A document loaded into an `IonCatalog`.
+pub struct CatalogEntry {
+    /// The file the document was read from.
+    pub path: PathBuf,
+    pub document: IonDocument,
+}
+
+#[derive(Debug, Clone, Default)]
+/// Every matching Ion file under a directory, parsed and keyed for lookup.
+///
+/// Files are keyed by their path relative to the directory, with `/` separators and without the
+/// extension, e.g. `sprites/hero.sprite` for `sprites/hero.sprite.ion`, and map to the file's
+/// top-level values as an `IonDocument`. Structs keyed by `CatalogOptions::id_field` each map to
+/// a document of their own.
+pub struct IonCatalog {
+    entries: HashMap<String, CatalogEntry>,
+}
+
+impl IonCatalog {
+    /// Loads every matching file under `dir`. If any file fails to load, returns a `LoadFailed`
+    /// error listing all of them.
+    pub fn load(dir: impl AsRef<Path>, options: &CatalogOptions) -> IonResult<IonCatalog> {
+        let (catalog, failures) = IonCatalog::load_partial(dir, options);
+        if failures.is_empty() { Ok(catalog) }
+        else { Err(IonError::new(IonErrorType::LoadFailed(failures), Vec::new())) }
+    }
+
+    /// Loads every matching file under `dir`, returning the files which did load along with the
+    /// errors for those which didn't.
+    pub fn load_partial(dir: impl AsRef<Path>, options: &CatalogOptions) -> (IonCatalog, Vec<(PathBuf, IonError)>) {
+        let dir = dir.as_ref();
+        let mut catalog = IonCatalog::default();
+        let mut failures = Vec::new();
+        let walk = WalkDir::new(dir).follow_links(options.follow_links).sort_by_file_name();
+        for entry in walk {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    let path = e.path().unwrap_or(dir).to_path_buf();
+                    failures.push((path, IonError::from(std::io::Error::from(e))));
+                    continue;
+                }
+            };
+            if !entry.file_type().is_file() { continue; }
+            let key = match IonCatalog::path_key(dir, entry.path(), &options.extensions) {
+                Some(key) => key,
+                None => continue,
+            };
+            if let Err(e) = catalog.load_file(entry.path(), key, options) {
+                failures.push((entry.path().to_path_buf(), e));
+            }
+        }
+        (catalog, failures)
+    }
+
+    /// Returns the key for `path`, or `None` if it doesn't have one of the extensions.
+    fn path_key(dir: &Path, path: &Path, extensions: &[String]) -> Option<String> {
+        let relative = path.strip_prefix(dir).unwrap_or(path);
+        let name = relative.to_string_lossy().replace('\\', "/");
+        extensions.iter()
+            .find_map(|ext| name.strip_suffix(&format!(".{}", ext)))
+            .map(str::to_string)
+    }
+
+    fn load_file(&mut self, path: &Path, key: String, options: &CatalogOptions) -> IonResult<()> {
+        let values = IonReader::read_file_with(path, &options.read)?.into_values();
+        let mut keyed = Vec::new();
+        let mut rest = Vec::new();
+        if let Some(id_field) = &options.id_field {
+            for value in values {
+                let id = match value.as_struct().and_then(|st| st.field(id_field)) {
+                    Some(id) => id.as_text().map(str::to_string).ok_or_else(|| IonError::new(
+                        IonErrorType::InvalidValue(format!("`{}` must be a string or symbol", id_field)),
+                        vec![id_field.clone()],
+                    ))?,
+                    None => {
+                        rest.push(value);
+                        continue;
+                    }
+                };
+                keyed.push((id, IonDocument::new(vec![value])));
+            }
+        }
+        else {
+            rest = values;
+        }
+        if options.id_field.is_none() || !rest.is_empty() {
+            keyed.push((key, IonDocument::new(rest)));
+        }
+        // check every key before inserting any, so a failed file leaves nothing behind
+        for (i, (key, _)) in keyed.iter().enumerate() {
+            let existing = self.entries.get(key).map(|entry| entry.path.as_path())
+                .or_else(|| keyed[..i].iter().any(|(k, _)| k == key).then_some(path));
+            if let Some(existing) = existing {
+                return Err(IonError::new(
+                    IonErrorType::InvalidValue(format!("`{}` is already defined in {}", key, existing.display())),
+                    Vec::new(),
+                ));
+            }
+        }
+        for (key, document) in keyed {
+            self.entries.insert(key, CatalogEntry { path: path.to_path_buf(), document });
+        }
+        Ok(())
+    }
+
+    /// Returns the number of documents in the catalog.
+    pub fn len(&self) -> usize { self.entries.len() }
+
+    /// Returns true if the catalog has no documents.
+    pub fn is_empty(&self) -> bool { self.entries.is_empty() }
+
+    /// Returns an iterator over the keys of all documents, in no particular order.
+    pub fn keys(&self) -> impl Iterator<Item = &String> { self.entries.keys() }
+
+    /// Returns the entry with the given key.
+    pub fn entry(&self, key: &str) -> Option<&CatalogEntry> { self.entries.get(key) }
+
+    /// Returns the document with the given key.
+    pub fn document(&self, key: &str) -> Option<&IonDocument> { self.entries.get(key).map(|entry| &entry.document) }
+
+    fn find(&self, key: &str) -> IonResult<&IonDocument> {
+        self.document(key).ok_or_else(|| IonError::new(
+            IonErrorType::ReferenceError(format!("unknown document `{}`", key)),
+            Vec::new(),
+        ))
+    }
+
+    /// Returns an `IonWalker` over the only value of the document with the given key, scoped to
+    /// that key. Errors with `WrongSize` if the document doesn't have exactly one value.
+    pub fn walker(&self, key: &str) -> IonResult<IonWalker<'_>> {
+        let value = self.find(key)?.single().map_err(|e| IonError::new(e.ty, vec![key.to_string()]))?;
+        Ok(IonWalker::with_scopes(value, vec![key.to_string()]))
+    }
+
+    /// Deserializes the only value of the document with the given key, e.g.
+    /// `catalog.get::<Sprite>("hero")`.
+    pub fn get<T: IonDeserialize>(&self, key: &str) -> IonResult<T> {
+        T::deserialize(&self.walker(key)?)
+    }
+
+    /// Deserializes every value of the document with the given key. Errors are scoped by the
+    /// key and the value's position.
+    pub fn get_all<T: IonDeserialize>(&self, key: &str) -> IonResult<Vec<T>> {
+        self.find(key)?.iter().enumerate()
+            .map(|(i, value)| T::deserialize(&IonWalker::with_scopes(value, vec![key.to_string(), format!("[{}]", i)])))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{value, write_files};
+
+    fn by_id() -> CatalogOptions {
+        CatalogOptions { id_field: Some("id".to_string()), ..CatalogOptions::default() }
+    }
+
+    fn sorted_keys(catalog: &IonCatalog) -> Vec<&str> {
+        let mut keys: Vec<&str> = catalog.keys().map(String::as_str).collect();
+        keys.sort_unstable();
+        keys
+    }
+
+    #[test]
+    fn keys_files_by_path() {
+        let dir = write_files("catalog-paths", &[
+            ("sprites/hero.sprite.ion", "{ name: \"hero\" }"),
+            ("levels/one.ion", "1 2"),
+            ("notes.txt", "not ion"),
+        ]);
+        let catalog = IonCatalog::load(&dir, &CatalogOptions::default()).unwrap();
+        assert_eq!(sorted_keys(&catalog), vec!["levels/one", "sprites/hero.sprite"]);
+        assert_eq!(catalog.document("sprites/hero.sprite").unwrap().values(), &[value("{ name: \"hero\" }")]);
+        assert_eq!(catalog.document("levels/one").unwrap().values(), &[value("1"), value("2")]);
+        assert_eq!(catalog.entry("levels/one").unwrap().path, dir.join("levels/one.ion"));
+    }
+
+    #[test]
+    fn keys_structs_by_id_field() {
+        let dir = write_files("catalog-ids", &[
+            ("enemies.ion", "{ id: goblin, hp: 6 } { id: \"orc\", hp: 12 } 42"),
+            ("items.ion", "{ id: sword }"),
+        ]);
+        let catalog = IonCatalog::load(&dir, &by_id()).unwrap();
+        assert_eq!(sorted_keys(&catalog), vec!["enemies", "goblin", "orc", "sword"]);
+        assert_eq!(catalog.document("orc").unwrap().values(), &[value("{ id: \"orc\", hp: 12 }")]);
+        assert_eq!(catalog.document("enemies").unwrap().values(), &[value("42")]);
+    }
+
+    #[test]
+    fn rejects_duplicate_ids_without_keeping_part_of_the_file() {
+        let dir = write_files("catalog-duplicates", &[
+            ("a.ion", "{ id: goblin }"),
+            ("b.ion", "{ id: orc } { id: goblin }"),
+            ("c.ion", "{ id: troll } { id: troll }"),
+            ("d.ion", "{ id: ogre } { id: 1 }"),
+        ]);
+        let (catalog, failures) = IonCatalog::load_partial(&dir, &by_id());
+        assert_eq!(sorted_keys(&catalog), vec!["goblin"]);
+        let failed: Vec<&Path> = failures.iter().map(|(path, _)| path.as_path()).collect();
+        assert_eq!(failed, vec![dir.join("b.ion"), dir.join("c.ion"), dir.join("d.ion")]);
+        assert!(failures[0].1.to_string().contains("`goblin` is already defined in"), "{}", failures[0].1);
+        assert!(failures[1].1.to_string().contains("`troll` is already defined in"), "{}", failures[1].1);
+        assert_eq!(failures[2].1.scopes, vec!["id".to_string()]);
+    }
+
+    #[test]
+    fn aggregates_load_failures() {
+        let dir = write_files("catalog-failures", &[
+            ("good.ion", "{ x: 1 }"),
+            ("bad.ion", "{ x: "),
+            ("worse.ion", "[1, 2"),
+        ]);
+        match IonCatalog::load(&dir, &CatalogOptions::default()) {
+            Err(IonError { ty: IonErrorType::LoadFailed(failures), .. }) => {
+                let failed: Vec<&Path> = failures.iter().map(|(path, _)| path.as_path()).collect();
+                assert_eq!(failed, vec![dir.join("bad.ion"), dir.join("worse.ion")]);
+            }
+            other => panic!("expected a load failure, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn deserializes_entries() {
+        let dir = write_files("catalog-get", &[("name.ion", "\"hero\"")]);
+        let catalog = IonCatalog::load(&dir, &CatalogOptions::default()).unwrap();
+        assert_eq!(catalog.get::<String>("name").unwrap(), "hero");
+        assert_eq!(catalog.get::<i64>("name").unwrap_err().scopes, vec!["name".to_string()]);
+        match catalog.get::<String>("missing") {
+            Err(IonError { ty: IonErrorType::ReferenceError(msg), .. }) => assert_eq!(msg, "unknown document `missing`"),
+            other => panic!("expected a reference error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn keeps_several_values_apart_from_a_list() {
+        let dir = write_files("catalog-several", &[("pair.ion", "1 2"), ("list.ion", "[1, 2]")]);
+        let catalog = IonCatalog::load(&dir, &CatalogOptions::default()).unwrap();
+        assert_eq!(catalog.get_all::<i64>("pair").unwrap(), vec![1, 2]);
+        match catalog.get::<i64>("pair") {
+            Err(IonError { ty: IonErrorType::WrongSize { found: 2, expected: 1 }, scopes }) => assert_eq!(scopes, vec!["pair"]),
+            other => panic!("expected a wrong size error, got {:?}", other),
+        }
+        assert_eq!(catalog.get_all::<i64>("list").unwrap_err().scopes, vec!["list", "[0]"]);
+    }
+}
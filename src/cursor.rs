@@ -0,0 +1,156 @@
+use ion_c_sys::*;
+use ion_c_sys::reader::{IonCReader, IonCReaderHandle};
+use crate::error::{IonError, IonErrorType, IonResult};
+use crate::reader::IonReader;
+use crate::symbol::SymbolTable;
+use crate::types::{Annotations, IonType, IonValue, Symbol};
+
+/// Steps through a buffer of text or binary Ion one value at a time, only materializing the
+/// values asked for. Values which are skipped over are never decoded, so finding one entry in a
+/// large asset pack stays cheap.
+///
+/// ```ignore
+/// let mut cursor = IonCursor::new(&buf)?;
+/// while let Some(ty) = cursor.next_value()? {
+///     if ty == IonType::Struct && cursor.annotations()?.contains("texture") {
+///         textures.push(cursor.read_value()?);
+///     }
+/// }
+/// ```
+pub struct IonCursor<'a> {
+    reader: IonCReaderHandle<'a>,
+    current: Option<IonType>,
+    symbols: SymbolTable,
+}
+
+impl<'a> IonCursor<'a> {
+    /// Create a cursor positioned before the first top-level value in `buf`.
+    pub fn new(buf: &'a [u8]) -> IonResult<Self> {
+        Ok(IonCursor { reader: IonCReaderHandle::try_from(buf)?, current: None, symbols: SymbolTable::new() })
+    }
+
+    /// Moves to the next value in the current container (or at the top level), returning its
+    /// type, or `None` once there are no more.
+    pub fn next_value(&mut self) -> IonResult<Option<IonType>> {
+        let ty = self.reader.next()?;
+        self.current = match ty {
+            ION_TYPE_NONE | ION_TYPE_EOF => None,
+            // typed nulls like `null.int` are read as `IonValue::Null`
+            _ if self.reader.is_null()? => Some(IonType::Null),
+            _ => Some(ion_type(ty)?),
+        };
+        Ok(self.current)
+    }
+
+    /// Returns the type of the current value, or `None` if the cursor isn't on one.
+    pub fn ty(&self) -> Option<IonType> { self.current }
+
+    /// Returns the number of containers the cursor has stepped into.
+    pub fn depth(&self) -> IonResult<usize> {
+        Ok(self.reader.depth()? as usize)
+    }
+
+    /// Returns the current value's field name, or `None` if the cursor isn't inside a struct.
+    pub fn field_name(&mut self) -> IonResult<Option<Symbol>> {
+        if !self.reader.is_in_struct()? { return Ok(None); }
+        Ok(Some(IonReader::read_field_name(&mut self.reader, &mut self.symbols)?))
+    }
+
+    /// Returns the current value's annotations.
+    pub fn annotations(&mut self) -> IonResult<Annotations> {
+        IonReader::read_annotations(&mut self.reader, &mut self.symbols)
+    }
+
+    /// Reads the whole of the current value, including anything nested inside it.
+    pub fn read_value(&mut self) -> IonResult<IonValue> {
+        self.on_value()?;
+        IonReader::read_value(&mut self.reader, &mut self.symbols, None)
+    }
+
+    /// Moves into the current list or struct, before its first value.
+    pub fn step_in(&mut self) -> IonResult<()> {
+        match self.on_value()? {
+            IonType::List | IonType::Struct => {
+                self.reader.step_in()?;
+                self.current = None;
+                Ok(())
+            }
+            found => Err(IonError::new(IonErrorType::ExpectedContainer { found }, Vec::new())),
+        }
+    }
+
+    /// Moves out of the current container, skipping any of its values which haven't been
+    /// visited. The cursor ends up on the container itself, so call `next_value` to move past it.
+    pub fn step_out(&mut self) -> IonResult<()> {
+        self.reader.step_out()?;
+        self.current = None;
+        Ok(())
+    }
+
+    /// Moves to the field called `name` in the current struct, skipping the fields before it.
+    /// Returns false, leaving the cursor at the end of the struct, if there's no such field.
+    pub fn seek_field(&mut self, name: &str) -> IonResult<bool> {
+        while self.next_value()?.is_some() {
+            if self.field_name()?.is_some_and(|field| field == name) {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    fn on_value(&self) -> IonResult<IonType> {
+        self.current.ok_or_else(|| IonError::new(
+            IonErrorType::InvalidValue("the cursor isn't on a value".to_string()),
+            Vec::new(),
+        ))
+    }
+}
+
+fn ion_type(ty: ION_TYPE) -> IonResult<IonType> {
+    match ty {
+        ION_TYPE_NULL => Ok(IonType::Null),
+        ION_TYPE_BOOL => Ok(IonType::Boolean),
+        ION_TYPE_INT => Ok(IonType::Integer),
+        ION_TYPE_FLOAT | ION_TYPE_DECIMAL => Ok(IonType::Float),
+        ION_TYPE_TIMESTAMP => Ok(IonType::Timestamp),
+        ION_TYPE_STRING => Ok(IonType::String),
+        ION_TYPE_SYMBOL => Ok(IonType::Symbol),
+        ION_TYPE_BLOB => Ok(IonType::Blob),
+        ION_TYPE_LIST => Ok(IonType::List),
+        ION_TYPE_STRUCT => Ok(IonType::Struct),
+        ION_TYPE_SEXP => Err(IonError::new(IonErrorType::TypeNotSupported("SExpr"), Vec::new())),
+        ION_TYPE_CLOB => Err(IonError::new(IonErrorType::TypeNotSupported("Clob"), Vec::new())),
+        _ => Err(IonError::new(IonErrorType::TypeNotSupported("Datagram"), Vec::new())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_values_without_reading_everything() {
+        let buf = br#"texture::{ name: a } { name: b } texture::{ name: c, size: [1, 2] }"#;
+        let mut cursor = IonCursor::new(buf).unwrap();
+        let mut names = Vec::new();
+        while let Some(ty) = cursor.next_value().unwrap() {
+            if ty == IonType::Struct && cursor.annotations().unwrap().contains("texture") {
+                cursor.step_in().unwrap();
+                assert!(cursor.seek_field("name").unwrap());
+                names.push(cursor.read_value().unwrap().as_text().unwrap().to_string());
+                cursor.step_out().unwrap();
+            }
+        }
+        assert_eq!(names, vec!["a", "c"]);
+    }
+
+    #[test]
+    fn only_steps_into_containers() {
+        let mut cursor = IonCursor::new(b"1").unwrap();
+        assert_eq!(cursor.next_value().unwrap(), Some(IonType::Integer));
+        match cursor.step_in() {
+            Err(IonError { ty: IonErrorType::ExpectedContainer { found: IonType::Integer }, .. }) => {}
+            other => panic!("expected a container error, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,365 @@
+//! Generation of [Ion Schema](https://amazon-ion.github.io/ion-schema/) documents from the Rust
+//! types assets are deserialized into, so editors and other tools can check assets against the
+//! shape the game expects.
+//!
+//! Types report their shape through `IonSchemaDescribe`, and `SchemaGenerator` collects the
+//! shapes of a type and everything it refers to into ISL 2.0 type definitions which `IonSchema`
+//! can load.
+
+use std::collections::{HashMap, HashSet};
+use bytes::Bytes;
+use chrono::{DateTime, FixedOffset};
+use crate::document::IonDocument;
+use crate::error::{IonError, IonErrorType, IonResult};
+use crate::schema::IonSchema;
+use crate::types::{Annotations, IonList, IonStruct, IonValue, Symbol};
+
+/// Describes the Ion shape a type is deserialized from.
+///
+/// ```ignore
+/// impl IonSchemaDescribe for Sprite {
+///     const TYPE_NAME: Option<&'static str> = Some("sprite");
+///
+///     fn describe(generator: &mut SchemaGenerator) -> IonShape {
+///         IonShape::Struct(vec![
+///             FieldShape::new("name", generator.of::<String>()),
+///             FieldShape::new("frames", generator.of::<Vec<Frame>>()),
+///             FieldShape::new("next", generator.of::<Option<String>>()),
+///         ])
+///     }
+/// }
+/// ```
+pub trait IonSchemaDescribe {
+    /// The name to define this type's shape under. Types without one have their shape inlined
+    /// wherever they're used, and can't be passed to `SchemaGenerator::add`.
+    const TYPE_NAME: Option<&'static str> = None;
+
+    /// Returns this type's shape, using `generator` to describe the types it contains.
+    fn describe(generator: &mut SchemaGenerator) -> IonShape;
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// The shape of an Ion value, as reported by `IonSchemaDescribe`.
+pub enum IonShape {
+    /// Any non-null value.
+    Any,
+    Boolean,
+    Integer,
+    /// A float, or an integer (see `IonWalker::as_float`).
+    Number,
+    String,
+    Symbol,
+    /// A string or a symbol.
+    Text,
+    Timestamp,
+    Blob,
+    /// A list whose items all have the given shape.
+    List(Box<IonShape>),
+    /// A struct with any field names, whose values all have the given shape.
+    Map(Box<IonShape>),
+    /// A struct with exactly these fields.
+    Struct(Vec<FieldShape>),
+    /// The given shape, or null.
+    Nullable(Box<IonShape>),
+    /// One of several shapes, chosen by the value's annotation, as read by
+    /// `IonWalker::match_annotation`.
+    Tagged(Vec<(String, IonShape)>),
+    /// A type defined by name in the schema, see `SchemaGenerator::of`.
+    Named(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// A field of an `IonShape::Struct`.
+pub struct FieldShape {
+    pub name: String,
+    pub shape: IonShape,
+    pub required: bool,
+}
+
+impl FieldShape {
+    /// A field which is required, unless its shape is nullable (e.g. from an `Option`), in which
+    /// case it may also be left out.
+    pub fn new(name: impl Into<String>, shape: IonShape) -> Self {
+        let required = !matches!(shape, IonShape::Nullable(_));
+        FieldShape { name: name.into(), shape, required }
+    }
+
+    /// A field which may be left out, e.g. one read with `get_or_default`.
+    pub fn optional(name: impl Into<String>, shape: IonShape) -> Self {
+        FieldShape { name: name.into(), shape, required: false }
+    }
+}
+
+#[derive(Debug, Default)]
+/// Collects named type definitions from `IonSchemaDescribe` types and writes them out as ISL.
+pub struct SchemaGenerator {
+    types: Vec<(String, IonShape)>,
+    names: HashSet<String>,
+}
+
+impl SchemaGenerator {
+    pub fn new() -> Self { SchemaGenerator::default() }
+
+    /// Returns the ISL document for `T` and every type it refers to. Fails if `T` has no
+    /// `TYPE_NAME`.
+    pub fn generate<T: IonSchemaDescribe>() -> IonResult<IonDocument> {
+        let mut generator = SchemaGenerator::new();
+        generator.add::<T>()?;
+        Ok(generator.to_document())
+    }
+
+    /// Adds a definition for `T` (and the named types it refers to) to the schema. Fails if `T`
+    /// has no `TYPE_NAME`, since there'd be nothing to refer to the definition by.
+    pub fn add<T: IonSchemaDescribe>(&mut self) -> IonResult<&mut Self> {
+        if T::TYPE_NAME.is_none() {
+            return Err(IonError::new(IonErrorType::InvalidValue(format!(
+                "`{}` has no TYPE_NAME to define it under", std::any::type_name::<T>(),
+            )), Vec::new()));
+        }
+        self.of::<T>();
+        Ok(self)
+    }
+
+    /// Returns the shape to use for a value of type `T`. Named types are defined the first time
+    /// they're seen, and referred to by name, so recursive types are fine. If two types share a
+    /// name, the first one described is used.
+    pub fn of<T: IonSchemaDescribe>(&mut self) -> IonShape {
+        let name = match T::TYPE_NAME {
+            Some(name) => name,
+            None => return T::describe(self),
+        };
+        if self.names.insert(name.to_string()) {
+            let shape = T::describe(self);
+            self.types.push((name.to_string(), shape));
+        }
+        IonShape::Named(name.to_string())
+    }
+
+    /// Returns the names and shapes of the types defined so far.
+    pub fn types(&self) -> &[(String, IonShape)] { &self.types }
+
+    /// Returns the schema as a document of ISL 2.0 values, with types sorted by name.
+    pub fn to_document(&self) -> IonDocument {
+        let mut types: Vec<&(String, IonShape)> = self.types.iter().collect();
+        types.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut values = vec![
+            IonValue::Symbol(Symbol::new("$ion_schema_2_0"), Annotations::new()),
+            IonValue::Struct(IonStruct::new_empty(), Annotations::from(vec!["schema_header"])),
+        ];
+        for (name, shape) in types {
+            let mut def = type_def(shape);
+            def.insert("name", IonValue::Symbol(Symbol::new(name), Annotations::new()));
+            values.push(IonValue::Struct(def, Annotations::from(vec!["type"])));
+        }
+        values.push(IonValue::Struct(IonStruct::new_empty(), Annotations::from(vec!["schema_footer"])));
+        IonDocument::new(values)
+    }
+
+    /// Loads the generated document as an `IonSchema`, to validate values with.
+    pub fn to_schema(&self) -> IonResult<IonSchema> {
+        IonSchema::from_values(self.to_document().values())
+    }
+}
+
+/// Returns `shape` as the constraints of an inline type definition.
+fn type_def(shape: &IonShape) -> IonStruct {
+    match type_ref(shape) {
+        IonValue::Struct(def, ann) if ann.is_empty() => def,
+        other => {
+            let mut def = IonStruct::new_empty();
+            def.insert("type", other);
+            def
+        }
+    }
+}
+
+/// Returns `shape` as a type reference: a type name where there is one, otherwise an inline
+/// type definition.
+fn type_ref(shape: &IonShape) -> IonValue {
+    let name = match shape {
+        IonShape::Any => "any",
+        IonShape::Boolean => "bool",
+        IonShape::Integer => "int",
+        IonShape::Number => "number",
+        IonShape::String => "string",
+        IonShape::Symbol => "symbol",
+        IonShape::Text => "text",
+        IonShape::Timestamp => "timestamp",
+        IonShape::Blob => "blob",
+        IonShape::Named(name) => name,
+        IonShape::List(item) => return constraints(vec![("type", symbol("list")), ("element", type_ref(item))]),
+        IonShape::Map(item) => return constraints(vec![("type", symbol("struct")), ("element", type_ref(item))]),
+        IonShape::Struct(fields) => {
+            let mut field_defs = IonStruct::new_empty();
+            for field in fields {
+                let mut field_ref = type_ref(&field.shape);
+                if field.required {
+                    let mut def = type_def(&field.shape);
+                    def.insert("occurs", symbol("required"));
+                    field_ref = IonValue::Struct(def, Annotations::new());
+                }
+                field_defs.insert(field.name.as_str(), field_ref);
+            }
+            return constraints(vec![
+                ("type", symbol("struct")),
+                ("fields", IonValue::Struct(field_defs, Annotations::from(vec!["closed"]))),
+            ]);
+        }
+        IonShape::Nullable(inner) => {
+            let mut inner = type_ref(inner);
+            inner.annotations_mut().push("$null_or");
+            return inner;
+        }
+        IonShape::Tagged(variants) => {
+            let variants = variants.iter()
+                .map(|(tag, shape)| {
+                    let mut def = type_def(shape);
+                    let tags = IonList { items: vec![symbol(tag)] };
+                    def.insert("annotations", IonValue::List(tags, Annotations::from(vec!["closed", "required"])));
+                    IonValue::Struct(def, Annotations::new())
+                })
+                .collect();
+            return constraints(vec![("one_of", IonValue::List(IonList { items: variants }, Annotations::new()))]);
+        }
+    };
+    symbol(name)
+}
+
+fn symbol(name: &str) -> IonValue {
+    IonValue::Symbol(Symbol::new(name), Annotations::new())
+}
+
+fn constraints(fields: Vec<(&str, IonValue)>) -> IonValue {
+    let fields: HashMap<Symbol, IonValue> = fields.into_iter().map(|(name, value)| (Symbol::new(name), value)).collect();
+    IonValue::Struct(IonStruct::new(fields), Annotations::new())
+}
+
+macro_rules! describe_as {
+    ($shape:ident: $($ty:ty),*) => {
+        $(
+            impl IonSchemaDescribe for $ty {
+                fn describe(_: &mut SchemaGenerator) -> IonShape { IonShape::$shape }
+            }
+        )*
+    };
+}
+
+describe_as!(Boolean: bool);
+describe_as!(Integer: i8, i16, i32, i64, u8, u16, u32, u64, usize);
+describe_as!(Number: f32, f64);
+describe_as!(String: String);
+describe_as!(Symbol: Symbol);
+describe_as!(Timestamp: DateTime<FixedOffset>);
+describe_as!(Blob: Bytes);
+
+impl IonSchemaDescribe for IonValue {
+    fn describe(_: &mut SchemaGenerator) -> IonShape { IonShape::Nullable(Box::new(IonShape::Any)) }
+}
+
+impl<T: IonSchemaDescribe> IonSchemaDescribe for Option<T> {
+    fn describe(generator: &mut SchemaGenerator) -> IonShape {
+        IonShape::Nullable(Box::new(generator.of::<T>()))
+    }
+}
+
+impl<T: IonSchemaDescribe> IonSchemaDescribe for Vec<T> {
+    fn describe(generator: &mut SchemaGenerator) -> IonShape {
+        IonShape::List(Box::new(generator.of::<T>()))
+    }
+}
+
+impl<T: IonSchemaDescribe> IonSchemaDescribe for HashMap<String, T> {
+    fn describe(generator: &mut SchemaGenerator) -> IonShape {
+        IonShape::Map(Box::new(generator.of::<T>()))
+    }
+}
+
+impl<T: IonSchemaDescribe> IonSchemaDescribe for Box<T> {
+    fn describe(generator: &mut SchemaGenerator) -> IonShape {
+        generator.of::<T>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use super::*;
+    use crate::test_util::value;
+
+    struct Frame;
+    struct Sprite;
+    struct Effect;
+
+    impl IonSchemaDescribe for Frame {
+        const TYPE_NAME: Option<&'static str> = Some("frame");
+
+        fn describe(generator: &mut SchemaGenerator) -> IonShape {
+            IonShape::Struct(vec![
+                FieldShape::new("duration", generator.of::<f64>()),
+                FieldShape::optional("flip", generator.of::<bool>()),
+            ])
+        }
+    }
+
+    impl IonSchemaDescribe for Effect {
+        fn describe(generator: &mut SchemaGenerator) -> IonShape {
+            IonShape::Tagged(vec![
+                ("shake".to_string(), IonShape::Struct(vec![FieldShape::new("strength", generator.of::<f64>())])),
+                ("sound".to_string(), IonShape::Text),
+            ])
+        }
+    }
+
+    impl IonSchemaDescribe for Sprite {
+        const TYPE_NAME: Option<&'static str> = Some("sprite");
+
+        fn describe(generator: &mut SchemaGenerator) -> IonShape {
+            IonShape::Struct(vec![
+                FieldShape::new("name", generator.of::<String>()),
+                FieldShape::new("frames", generator.of::<Vec<Frame>>()),
+                FieldShape::new("next", generator.of::<Option<String>>()),
+                FieldShape::new("tags", generator.of::<HashMap<String, i64>>()),
+                FieldShape::new("effect", generator.of::<Option<Effect>>()),
+            ])
+        }
+    }
+
+    #[test]
+    fn generated_schema_round_trips() {
+        let doc = SchemaGenerator::generate::<Sprite>().unwrap();
+        let names: Vec<String> = doc.iter().filter_map(|v| v.as_struct()?.field("name")?.as_text().map(str::to_string)).collect();
+        assert_eq!(names, vec!["frame", "sprite"]);
+
+        let schema = IonSchema::from_values(doc.values()).unwrap();
+        let sprite = value(r#"{
+            name: "hero",
+            frames: [{ duration: 0.5e0 }, { duration: 1, flip: true }],
+            next: null,
+            tags: { layer: 2 },
+            effect: shake::{ strength: 2.0e0 },
+        }"#);
+        assert_eq!(schema.validate("sprite", &sprite).unwrap(), vec![]);
+    }
+
+    #[rstest(text,
+        case(r#"{ frames: [], tags: {} }"#),
+        case(r#"{ name: "hero", frames: [{ flip: true }], tags: {} }"#),
+        case(r#"{ name: "hero", frames: [], tags: { layer: "top" } }"#),
+        case(r#"{ name: "hero", frames: [], tags: {}, speed: 2 }"#),
+        case(r#"{ name: "hero", frames: [], tags: {}, effect: explode::{} }"#),
+    )]
+    fn generated_schema_rejects_mismatches(text: &str) {
+        let schema = SchemaGenerator::generate::<Sprite>().and_then(|doc| IonSchema::from_values(doc.values())).unwrap();
+        assert!(!schema.validate("sprite", &value(text)).unwrap().is_empty(), "{} passed", text);
+    }
+
+    #[test]
+    fn add_requires_a_type_name() {
+        assert!(SchemaGenerator::new().add::<Vec<Frame>>().is_err());
+        assert!(SchemaGenerator::new().add::<Effect>().is_err());
+        let mut generator = SchemaGenerator::new();
+        generator.add::<Frame>().unwrap().add::<Sprite>().unwrap();
+        assert_eq!(generator.types().len(), 2);
+        assert!(generator.to_schema().unwrap().has_type("frame"));
+    }
+}
@@ -0,0 +1,255 @@
+//! Structural comparison of two `IonValue` documents.
+//!
+//! Unlike a textual diff, struct field order and formatting don't matter: only the values do.
+//! Lists are compared with a longest common subsequence, so inserting one element shows up as a
+//! single `Inserted` change instead of every later element changing.
+
+use std::fmt::{Display, Formatter};
+use crate::path::IonPath;
+use crate::types::{Annotations, IonList, IonValue, Symbol};
+
+/// Lists whose differing middle section would need more LCS table cells than this are compared
+/// element by element instead, so diffing huge arrays (e.g. vertex data) stays fast.
+const MAX_LCS_CELLS: usize = 4_000_000;
+
+#[derive(Debug, Clone, PartialEq)]
+/// What changed at a path.
+pub enum ChangeKind {
+    /// A struct field only present in the new document.
+    Added(IonValue),
+    /// A struct field only present in the old document.
+    Removed(IonValue),
+    /// A scalar replaced by a different value of the same type.
+    Changed { old: IonValue, new: IonValue },
+    /// A value replaced by a value of a different type.
+    TypeChanged { old: IonValue, new: IonValue },
+    /// A container's annotations changed. Changes to its contents are listed separately.
+    AnnotationsChanged { old: Annotations, new: Annotations },
+    /// A list element only present in the new document. The path's last index is its position
+    /// in the new list.
+    Inserted(IonValue),
+    /// A list element only present in the old document. The path's last index is its position
+    /// in the old list.
+    Deleted(IonValue),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// A single difference between two documents.
+///
+/// List indices in `path` refer to the old document, except for the last index of an
+/// `Inserted` change, which is a position in the new list.
+pub struct Change {
+    pub path: IonPath,
+    pub kind: ChangeKind,
+}
+
+/// Returns every difference between `old` and `new`, in document order
+/// (struct fields are visited in name order). An empty result means the documents are equal.
+pub fn diff(old: &IonValue, new: &IonValue) -> Vec<Change> {
+    let mut changes = Vec::new();
+    diff_value(&IonPath::root(), old, new, &mut changes);
+    changes
+}
+
+/// Renders changes one per line, prefixed with `+` (added), `-` (removed) or `~` (changed).
+pub fn render(changes: &[Change]) -> String {
+    changes.iter().map(|change| format!("{}\n", change)).collect()
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            ChangeKind::Added(value) | ChangeKind::Inserted(value) => write!(f, "+ {}: {}", self.path, value),
+            ChangeKind::Removed(value) | ChangeKind::Deleted(value) => write!(f, "- {}: {}", self.path, value),
+            ChangeKind::Changed { old, new } => write!(f, "~ {}: {} -> {}", self.path, old, new),
+            ChangeKind::TypeChanged { old, new } => {
+                write!(f, "~ {}: {:?} {} -> {:?} {}", self.path, old.ty(), old, new.ty(), new)
+            }
+            ChangeKind::AnnotationsChanged { old, new } => {
+                write!(f, "~ {}: annotations [{}] -> [{}]", self.path, old.join(", "), new.join(", "))
+            }
+        }
+    }
+}
+
+/// Compares two values for equality, treating NaN as equal to itself.
+pub(crate) fn same(a: &IonValue, b: &IonValue) -> bool {
+    if a.annotations() != b.annotations() { return false; }
+    match (a, b) {
+        (IonValue::Float(x, _), IonValue::Float(y, _)) => x == y || (x.is_nan() && y.is_nan()),
+        (IonValue::List(x, _), IonValue::List(y, _)) => {
+            x.len() == y.len() && x.iter().zip(y.iter()).all(|(a, b)| same(a, b))
+        }
+        (IonValue::Struct(x, _), IonValue::Struct(y, _)) => {
+            x.iter_fields().count() == y.iter_fields().count()
+                && x.iter_fields().all(|(name, a)| y.field(name).is_some_and(|b| same(a, b)))
+        }
+        _ => a == b,
+    }
+}
+
+fn sorted_names<'v>(a: &'v IonValue, b: &'v IonValue) -> Vec<&'v Symbol> {
+    let mut names: Vec<&Symbol> = a.as_struct().into_iter().chain(b.as_struct())
+        .flat_map(|st| st.iter_fields().map(|(name, _)| name))
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+fn diff_value(path: &IonPath, old: &IonValue, new: &IonValue, out: &mut Vec<Change>) {
+    if old.ty() != new.ty() {
+        out.push(Change { path: path.clone(), kind: ChangeKind::TypeChanged { old: old.clone(), new: new.clone() } });
+        return;
+    }
+    match (old, new) {
+        (IonValue::List(a, _), IonValue::List(b, _)) => {
+            diff_annotations(path, old, new, out);
+            diff_list(path, a, b, out);
+        }
+        (IonValue::Struct(a, _), IonValue::Struct(b, _)) => {
+            diff_annotations(path, old, new, out);
+            for name in sorted_names(old, new) {
+                let path = path.field(name);
+                match (a.field(name), b.field(name)) {
+                    (Some(x), Some(y)) => diff_value(&path, x, y, out),
+                    (Some(x), None) => out.push(Change { path, kind: ChangeKind::Removed(x.clone()) }),
+                    (None, Some(y)) => out.push(Change { path, kind: ChangeKind::Added(y.clone()) }),
+                    (None, None) => unreachable!("field names come from one of the structs"),
+                }
+            }
+        }
+        _ => {
+            if !same(old, new) {
+                // the new value carries its annotations, so a separate annotation change isn't needed
+                out.push(Change { path: path.clone(), kind: ChangeKind::Changed { old: old.clone(), new: new.clone() } });
+            }
+        }
+    }
+}
+
+fn diff_annotations(path: &IonPath, old: &IonValue, new: &IonValue, out: &mut Vec<Change>) {
+    if old.annotations() != new.annotations() {
+        out.push(Change {
+            path: path.clone(),
+            kind: ChangeKind::AnnotationsChanged { old: old.annotations().clone(), new: new.annotations().clone() },
+        });
+    }
+}
+
+fn diff_list(path: &IonPath, old: &IonList, new: &IonList, out: &mut Vec<Change>) {
+    let (a, b) = (&old.items[..], &new.items[..]);
+    // trim the common prefix and suffix, which is usually most of the list
+    let prefix = a.iter().zip(b.iter()).take_while(|(x, y)| same(x, y)).count();
+    let suffix = a[prefix..].iter().rev().zip(b[prefix..].iter().rev()).take_while(|(x, y)| same(x, y)).count();
+    let (a_mid, b_mid) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+    let matches = if a_mid.len().saturating_mul(b_mid.len()) <= MAX_LCS_CELLS { lcs(a_mid, b_mid) }
+                  else { Vec::new() };
+    // walk the gaps between matched elements
+    let (mut i, mut j) = (0, 0);
+    for (mi, mj) in matches.into_iter().chain(std::iter::once((a_mid.len(), b_mid.len()))) {
+        diff_gap(path, prefix, (i, mi), (j, mj), a_mid, b_mid, out);
+        i = mi + 1;
+        j = mj + 1;
+    }
+}
+
+/// Emits changes for `a[old.0..old.1]` becoming `b[new.0..new.1]`, where `offset` is where
+/// `a` and `b` start in their lists. Elements are compared pairwise where both sides have one,
+/// so an edited struct in a list shows up as the fields that changed rather than a replacement.
+fn diff_gap(path: &IonPath, offset: usize, old: (usize, usize), new: (usize, usize),
+            a: &[IonValue], b: &[IonValue], out: &mut Vec<Change>)
+{
+    let paired = (old.1 - old.0).min(new.1 - new.0);
+    for k in 0..paired {
+        diff_value(&path.index(offset + old.0 + k), &a[old.0 + k], &b[new.0 + k], out);
+    }
+    for (i, item) in a.iter().enumerate().take(old.1).skip(old.0 + paired) {
+        out.push(Change { path: path.index(offset + i), kind: ChangeKind::Deleted(item.clone()) });
+    }
+    for (j, item) in b.iter().enumerate().take(new.1).skip(new.0 + paired) {
+        out.push(Change { path: path.index(offset + j), kind: ChangeKind::Inserted(item.clone()) });
+    }
+}
+
+/// Returns the index pairs of a longest common subsequence of `a` and `b`, in order.
+fn lcs(a: &[IonValue], b: &[IonValue]) -> Vec<(usize, usize)> {
+    let (n, m) = (a.len(), b.len());
+    // table[i][j] is the LCS length of a[i..] and b[j..]
+    let mut table = vec![0u32; (n + 1) * (m + 1)];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            table[i * (m + 1) + j] = if same(&a[i], &b[j]) { table[(i + 1) * (m + 1) + j + 1] + 1 }
+                                     else { table[(i + 1) * (m + 1) + j].max(table[i * (m + 1) + j + 1]) };
+        }
+    }
+    let mut pairs = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if same(&a[i], &b[j]) {
+            pairs.push((i, j));
+            i += 1;
+            j += 1;
+        }
+        else if table[(i + 1) * (m + 1) + j] >= table[i * (m + 1) + j + 1] { i += 1 }
+        else { j += 1 }
+    }
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use super::*;
+    use crate::test_util::value;
+
+    fn changes(old: &str, new: &str) -> Vec<String> {
+        diff(&value(old), &value(new)).iter().map(|change| change.to_string()).collect()
+    }
+
+    #[rstest(old, new, expected,
+        case("[1, 2, 3]", "[1, 2, 3]", &[]),
+        case("[1, 2, 3]", "[1, 9, 2, 3]", &["+ [1]: 9"]),
+        case("[1, 2, 3]", "[1, 3]", &["- [1]: 2"]),
+        case("[1, 2, 3, 4]", "[2, 3, 4, 1]", &["- [0]: 1", "+ [3]: 1"]),
+        case("[1, 2, 3]", "[1, 5, 3]", &["~ [1]: 2 -> 5"]),
+        case("[a, b, c, d]", "[x, a, c, d, y]", &["+ [0]: x", "- [1]: b", "+ [4]: y"]),
+        case("[]", "[1, 2]", &["+ [0]: 1", "+ [1]: 2"]),
+    )]
+    fn diffs_lists(old: &str, new: &str, expected: &[&str]) {
+        assert_eq!(changes(old, new), expected);
+    }
+
+    #[test]
+    fn diffs_nested_structs() {
+        let old = r#"{ name: "hero", stats: { hp: 10, mp: 5 }, frames: [{ d: 1 }, { d: 2 }] }"#;
+        let new = r#"{ name: "hero", stats: { hp: 12, xp: 0 }, frames: [{ d: 1 }, { d: 3 }], tag: boss }"#;
+        assert_eq!(changes(old, new), vec![
+            "~ frames[1].d: 2 -> 3",
+            "~ stats.hp: 10 -> 12",
+            "- stats.mp: 5",
+            "+ stats.xp: 0",
+            "+ tag: boss",
+        ]);
+    }
+
+    #[test]
+    fn reports_change_paths_and_kinds() {
+        let found = diff(&value("{ a: [1, { b: 2 }] }"), &value("{ a: [1, { b: \"2\" }] }"));
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].path.to_string(), "a[1].b");
+        assert!(matches!(found[0].kind, ChangeKind::TypeChanged { .. }));
+    }
+
+    #[test]
+    fn diffs_annotations() {
+        assert_eq!(changes("a::{ x: 1 }", "b::{ x: 1 }"), vec!["~ (root): annotations [a] -> [b]"]);
+        assert_eq!(changes("a::1", "b::1"), vec!["~ (root): a::1 -> b::1"]);
+    }
+
+    #[test]
+    fn nan_equals_itself() {
+        assert!(diff(&value("[nan]"), &value("[nan]")).is_empty());
+    }
+}
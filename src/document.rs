@@ -0,0 +1,136 @@
+use std::ops::Index;
+use crate::error::{IonError, IonErrorType, IonResult};
+use crate::traits::IonDeserialize;
+use crate::types::IonValue;
+use crate::walker::IonWalker;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+/// The top-level values of an Ion stream (a datagram), in order, as returned by `IonReader`.
+///
+/// Unlike a list, a document has no annotations of its own, and writing it back out with
+/// `IonWriter::write_document` produces the same top-level values rather than one list.
+pub struct IonDocument {
+    values: Vec<IonValue>,
+}
+
+impl IonDocument {
+    /// Create a document with the given top-level values.
+    pub fn new(values: Vec<IonValue>) -> Self {
+        IonDocument { values }
+    }
+
+    /// Returns the top-level values.
+    pub fn values(&self) -> &[IonValue] { &self.values }
+
+    /// Returns the top-level values for modification.
+    pub fn values_mut(&mut self) -> &mut Vec<IonValue> { &mut self.values }
+
+    /// Consumes the document, returning its top-level values.
+    pub fn into_values(self) -> Vec<IonValue> { self.values }
+
+    /// Returns the number of top-level values.
+    pub fn len(&self) -> usize { self.values.len() }
+
+    /// Returns true if the document has no values.
+    pub fn is_empty(&self) -> bool { self.values.is_empty() }
+
+    /// Returns an iterator over the top-level values.
+    pub fn iter(&self) -> std::slice::Iter<'_, IonValue> { self.values.iter() }
+
+    /// Returns the document's only value, or a `WrongSize` error if it doesn't have exactly one.
+    pub fn single(&self) -> IonResult<&IonValue> {
+        match self.values.as_slice() {
+            [value] => Ok(value),
+            values => Err(IonError::new(IonErrorType::WrongSize { found: values.len(), expected: 1 }, Vec::new())),
+        }
+    }
+
+    /// Consumes the document, returning its only value, or a `WrongSize` error if it doesn't have
+    /// exactly one.
+    pub fn into_single(mut self) -> IonResult<IonValue> {
+        self.single()?;
+        Ok(self.values.remove(0))
+    }
+
+    /// Deserializes the document's only value.
+    pub fn deserialize<T: IonDeserialize>(&self) -> IonResult<T> {
+        T::deserialize(&IonWalker::new(self.single()?))
+    }
+
+    /// Deserializes every top-level value as a `T`. Errors are scoped by the value's position.
+    pub fn deserialize_all<T: IonDeserialize>(&self) -> IonResult<Vec<T>> {
+        self.values.iter().enumerate()
+            .map(|(i, value)| T::deserialize(&IonWalker::with_scopes(value, vec![format!("[{}]", i)])))
+            .collect()
+    }
+}
+
+impl From<Vec<IonValue>> for IonDocument {
+    fn from(values: Vec<IonValue>) -> Self { IonDocument::new(values) }
+}
+
+impl FromIterator<IonValue> for IonDocument {
+    fn from_iter<I: IntoIterator<Item = IonValue>>(iter: I) -> Self {
+        IonDocument::new(iter.into_iter().collect())
+    }
+}
+
+impl IntoIterator for IonDocument {
+    type Item = IonValue;
+    type IntoIter = std::vec::IntoIter<IonValue>;
+    fn into_iter(self) -> Self::IntoIter { self.values.into_iter() }
+}
+
+impl<'a> IntoIterator for &'a IonDocument {
+    type Item = &'a IonValue;
+    type IntoIter = std::slice::Iter<'a, IonValue>;
+    fn into_iter(self) -> Self::IntoIter { self.values.iter() }
+}
+
+impl Index<usize> for IonDocument {
+    type Output = IonValue;
+    fn index(&self, index: usize) -> &IonValue { &self.values[index] }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reader::IonReader;
+    use crate::test_util::value;
+
+    fn doc(text: &str) -> IonDocument {
+        IonReader::read_string(text).unwrap()
+    }
+
+    #[test]
+    fn keeps_top_level_values_in_order() {
+        let doc = doc("1 two [3]");
+        assert_eq!(doc.len(), 3);
+        assert!(!doc.is_empty());
+        assert_eq!(doc[1], value("two"));
+        assert_eq!(doc.iter().collect::<Vec<_>>(), vec![&value("1"), &value("two"), &value("[3]")]);
+        assert_eq!(doc.clone().into_iter().collect::<IonDocument>(), doc);
+        assert_eq!(IonDocument::from(doc.values().to_vec()), doc);
+        assert!(IonDocument::default().is_empty());
+    }
+
+    #[test]
+    fn requires_a_single_value() {
+        assert_eq!(doc("{ x: 1 }").single().unwrap(), &value("{ x: 1 }"));
+        assert_eq!(doc("{ x: 1 }").into_single().unwrap(), value("{ x: 1 }"));
+        for (text, found) in [("", 0), ("1 2", 2)] {
+            match doc(text).into_single() {
+                Err(IonError { ty: IonErrorType::WrongSize { found: f, expected: 1 }, .. }) => assert_eq!(f, found),
+                other => panic!("expected a size error, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn deserializes_values() {
+        assert_eq!(doc("3").deserialize::<i64>().unwrap(), 3);
+        assert!(doc("3 4").deserialize::<i64>().is_err());
+        assert_eq!(doc("3 4").deserialize_all::<i64>().unwrap(), vec![3, 4]);
+        assert_eq!(doc("3 four").deserialize_all::<i64>().unwrap_err().scopes, vec!["[1]"]);
+    }
+}
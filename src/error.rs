@@ -1,5 +1,7 @@
+use std::fmt::{Display, Formatter};
 use std::io::Error;
-use ion_c_sys::result::IonCError;
+use std::path::PathBuf;
+use ion_c_sys::result::{IonCError, LineColumn, Position};
 use crate::types::IonType;
 
 pub type IonResult<T> = Result<T, IonError>;
@@ -13,19 +15,126 @@ impl IonError {
     pub fn new(ty: IonErrorType, scopes: Vec<String>) -> Self {
         IonError { ty, scopes }
     }
+
+    /// Creates a `SyntaxError` for the given byte offset into `source`, computing its line and column.
+    pub(crate) fn syntax(source: &str, offset: usize, message: impl Into<String>) -> Self {
+        let before = &source[..offset.min(source.len())];
+        let line = before.matches('\n').count() + 1;
+        let column = before.chars().rev().take_while(|c| *c != '\n').count() + 1;
+        IonError::new(IonErrorType::SyntaxError { message: message.into(), line, column }, Vec::new())
+    }
+
+    /// Returns the scopes joined into a readable path, e.g. `sprite.frames[2]`.
+    pub fn path(&self) -> String {
+        let mut path = String::new();
+        for scope in self.scopes.iter() {
+            if !path.is_empty() && !scope.starts_with('[') {
+                path.push('.');
+            }
+            path.push_str(scope);
+        }
+        path
+    }
 }
 
+impl Display for IonError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.scopes.is_empty() {
+            write!(f, "{}", self.ty)
+        }
+        else {
+            write!(f, "{}: {}", self.path(), self.ty)
+        }
+    }
+}
+
+impl std::error::Error for IonError {}
+
 #[derive(Debug)]
 pub enum IonErrorType {
     InvalidValue(String),
     MissingField(String),
     WrongType { found: IonType, expected: IonType },
+    /// A list or struct was expected, e.g. to step into, but `found` was something else.
+    ExpectedContainer { found: IonType },
     WrongSize { found: usize, expected: usize },
     IoError(std::io::Error),
     ParseError(IonCError),
+    SyntaxError { message: String, line: usize, column: usize },
     TypeNotSupported(&'static str),
-    MissingAnnotation { expected: &'static [&'static str] },
+    /// None of a value's annotations were one of `expected`. `expected` was a
+    /// `&'static [&'static str]` before 0.0.2, which couldn't hold names only known at runtime.
+    MissingAnnotation { expected: Vec<String> },
     IndexOutOfBounds { tried: usize, bounds: (usize, usize) },
+    InvalidSchema(String),
+    MergeConflict(String),
+    PatchConflict(String),
+    /// An include directive couldn't be resolved. `chain` lists the files being read, outermost first.
+    IncludeError { message: String, chain: Vec<PathBuf> },
+    ReferenceError(String),
+    /// A struct field which nothing read, found by a strict `IonWalker`. `suggestion` is the
+    /// closest field name that was read, if any is close.
+    UnknownField { name: String, suggestion: Option<String> },
+    /// A deserialized value broke one of its `IonValidate` invariants.
+    Validation(String),
+    /// A migration step from version `from` to `to` failed, or there was no step to run.
+    Migration { from: u32, to: u32, message: String },
+    /// Some files failed to load, along with why.
+    LoadFailed(Vec<(PathBuf, IonError)>),
+}
+
+impl Display for IonErrorType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IonErrorType::InvalidValue(msg) => write!(f, "invalid value: {}", msg),
+            IonErrorType::MissingField(name) => write!(f, "missing field `{}`", name),
+            IonErrorType::WrongType { found, expected } =>
+                write!(f, "expected {:?}, found {:?}", expected, found),
+            IonErrorType::ExpectedContainer { found } =>
+                write!(f, "expected List or Struct, found {:?}", found),
+            IonErrorType::WrongSize { found, expected } =>
+                write!(f, "expected {} items, found {}", expected, found),
+            IonErrorType::IoError(e) => write!(f, "I/O error: {}", e),
+            IonErrorType::ParseError(e) => match e.position {
+                Position::OffsetLineColumn(_, LineColumn(line, column)) =>
+                    write!(f, "parse error at line {}, column {}: {}", line, column, e),
+                Position::Offset(bytes) => write!(f, "parse error at byte {}: {}", bytes, e),
+                Position::Unknown => write!(f, "parse error: {}", e),
+            },
+            IonErrorType::SyntaxError { message, line, column } =>
+                write!(f, "syntax error at line {}, column {}: {}", line, column, message),
+            IonErrorType::TypeNotSupported(ty) => write!(f, "type not supported: {}", ty),
+            IonErrorType::MissingAnnotation { expected } =>
+                write!(f, "missing annotation, expected one of: {}", expected.join(", ")),
+            IonErrorType::IndexOutOfBounds { tried, bounds } =>
+                write!(f, "index {} out of bounds ({}..{})", tried, bounds.0, bounds.1),
+            IonErrorType::InvalidSchema(msg) => write!(f, "invalid schema: {}", msg),
+            IonErrorType::MergeConflict(msg) => write!(f, "merge conflict: {}", msg),
+            IonErrorType::PatchConflict(msg) => write!(f, "patch does not apply: {}", msg),
+            IonErrorType::ReferenceError(msg) => write!(f, "{}", msg),
+            IonErrorType::UnknownField { name, suggestion: Some(suggestion) } =>
+                write!(f, "unknown field `{}`, did you mean `{}`?", name, suggestion),
+            IonErrorType::UnknownField { name, suggestion: None } => write!(f, "unknown field `{}`", name),
+            IonErrorType::Validation(msg) => write!(f, "{}", msg),
+            IonErrorType::Migration { from, to, message } =>
+                write!(f, "migration from version {} to {} failed: {}", from, to, message),
+            IonErrorType::LoadFailed(failures) => {
+                write!(f, "{} file(s) failed to load", failures.len())?;
+                for (path, e) in failures {
+                    write!(f, "\n  {}: {}", path.display(), e)?;
+                }
+                Ok(())
+            }
+            IonErrorType::IncludeError { message, chain } => {
+                write!(f, "{}", message)?;
+                if !chain.is_empty() {
+                    let files: Vec<String> = chain.iter().map(|p| p.display().to_string()).collect();
+                    write!(f, " (include chain: {})", files.join(" -> "))?;
+                }
+                Ok(())
+            }
+        }
+    }
 }
 
 impl From<std::io::Error> for IonError {
@@ -0,0 +1,157 @@
+use std::path::{Path, PathBuf};
+use crate::document::IonDocument;
+use crate::error::{IonError, IonErrorType, IonResult};
+use crate::path::IonPath;
+use crate::reader::{IonReader, ReadOptions};
+use crate::types::{Annotations, IonList, IonStruct, IonValue};
+
+/// Replaces include directives like `include::"common/palette.ion"` with the values of the
+/// file they name. A file with one top-level value is substituted for the directive; a file
+/// with several is spliced into the surrounding list, or becomes a list elsewhere.
+pub(crate) struct IncludeResolver<'o> {
+    options: &'o ReadOptions,
+    /// Canonical paths of the files currently being resolved, outermost first.
+    chain: Vec<PathBuf>,
+}
+
+impl<'o> IncludeResolver<'o> {
+    /// `file` is the document being resolved, or `None` if it didn't come from a file.
+    pub(crate) fn new(options: &'o ReadOptions, file: Option<&Path>) -> Self {
+        let chain = file.map(|f| f.canonicalize().unwrap_or_else(|_| f.to_path_buf())).into_iter().collect();
+        IncludeResolver { options, chain }
+    }
+
+    /// Resolves every directive in `doc`. Relative includes are looked up in `dir` first, then in
+    /// the search path.
+    pub(crate) fn resolve(&mut self, doc: IonDocument, dir: &Path) -> IonResult<IonDocument> {
+        Ok(IonDocument::new(self.resolve_items(doc.into_values(), dir, &IonPath::root())?))
+    }
+
+    fn error(&self, message: String, path: &IonPath) -> IonError {
+        IonError::new(IonErrorType::IncludeError { message, chain: self.chain.clone() }, path.to_scopes())
+    }
+
+    fn resolve_items(&mut self, items: Vec<IonValue>, dir: &Path, path: &IonPath) -> IonResult<Vec<IonValue>> {
+        let mut resolved = Vec::with_capacity(items.len());
+        for (i, item) in items.into_iter().enumerate() {
+            resolved.extend(self.resolve_value(item, dir, &path.index(i))?);
+        }
+        Ok(resolved)
+    }
+
+    fn resolve_one(&mut self, value: IonValue, dir: &Path, path: &IonPath) -> IonResult<IonValue> {
+        let mut values = self.resolve_value(value, dir, path)?;
+        if values.len() == 1 { Ok(values.remove(0)) }
+        else { Ok(IonValue::List(IonList { items: values }, Annotations::new())) }
+    }
+
+    /// Returns the values `value` resolves to: itself with its contents resolved, or the values
+    /// of the file it includes.
+    fn resolve_value(&mut self, value: IonValue, dir: &Path, path: &IonPath) -> IonResult<Vec<IonValue>> {
+        if value.has_annotation(&self.options.include_annotation) {
+            let target = match value.as_text() {
+                Some(target) => target,
+                None => return Err(self.error(format!("expected a file name, found {}", value), path)),
+            };
+            return self.include(target, dir, path);
+        }
+        match value {
+            IonValue::List(list, ann) => {
+                let items = self.resolve_items(list.items, dir, path)?;
+                Ok(vec![IonValue::List(IonList { items }, ann)])
+            }
+            IonValue::Struct(st, ann) => {
+                let mut resolved = IonStruct::new_empty();
+                for (name, field) in st.iter_fields() {
+                    resolved.insert(name.clone(), self.resolve_one(field.clone(), dir, &path.field(name))?);
+                }
+                Ok(vec![IonValue::Struct(resolved, ann)])
+            }
+            other => Ok(vec![other]),
+        }
+    }
+
+    fn find(&self, target: &str, dir: &Path) -> Option<PathBuf> {
+        let target = Path::new(target);
+        if target.is_absolute() {
+            return Some(target.to_path_buf()).filter(|p| p.is_file());
+        }
+        std::iter::once(dir).chain(self.options.search_path.iter().map(PathBuf::as_path))
+            .map(|base| base.join(target))
+            .find(|p| p.is_file())
+    }
+
+    fn include(&mut self, target: &str, dir: &Path, path: &IonPath) -> IonResult<Vec<IonValue>> {
+        let file = match self.find(target, dir) {
+            Some(file) => file,
+            None => return Err(self.error(format!("can't find included file \"{}\"", target), path)),
+        };
+        let canonical = file.canonicalize().unwrap_or_else(|_| file.clone());
+        if self.chain.contains(&canonical) {
+            self.chain.push(canonical);
+            let e = self.error("include cycle".to_string(), path);
+            self.chain.pop();
+            return Err(e);
+        }
+        self.chain.push(canonical);
+        let doc = match IonReader::read_file(&file) {
+            Ok(doc) => doc,
+            Err(e) => {
+                let e = self.error(e.to_string(), &IonPath::root());
+                self.chain.pop();
+                return Err(e);
+            }
+        };
+        let dir = file.parent().unwrap_or_else(|| Path::new("."));
+        let resolved = self.resolve_items(doc.into_values(), dir, &IonPath::root());
+        self.chain.pop();
+        resolved
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use super::*;
+    use crate::test_util::{value, write_files};
+
+    fn options() -> ReadOptions {
+        ReadOptions { includes: true, ..ReadOptions::default() }
+    }
+
+    #[test]
+    fn substitutes_and_splices_files() {
+        let dir = write_files("include-substitutes", &[
+            ("main.ion", r#"{ palette: include::"common/palette.ion", frames: [0, include::"frames.ion", 3] }"#),
+            ("common/palette.ion", r#"{ base: include::"base.ion" }"#),
+            ("common/base.ion", "red"),
+            ("frames.ion", "1 2"),
+        ]);
+        let doc = IonReader::read_file_with(dir.join("main.ion"), &options()).unwrap();
+        assert_eq!(doc.into_single().unwrap(), value("{ palette: { base: red }, frames: [0, 1, 2, 3] }"));
+    }
+
+    #[test]
+    fn searches_the_search_path() {
+        let dir = write_files("include-search", &[("lib/shared.ion", "42")]);
+        let options = ReadOptions { search_path: vec![dir.join("lib")], ..options() };
+        let doc = IonReader::read_string_with(r#"{ x: include::"shared.ion" }"#, &options).unwrap();
+        assert_eq!(doc.into_single().unwrap(), value("{ x: 42 }"));
+    }
+
+    #[rstest(test, files, message, chain_len,
+        case("missing", &[("main.ion", r#"[include::"nope.ion"]"#)], "can't find included file \"nope.ion\"", 1),
+        case("cycle", &[("main.ion", r#"[include::"a.ion"]"#), ("a.ion", r#"[include::"main.ion"]"#)], "include cycle", 3),
+        case("not_text", &[("main.ion", "[include::1]")], "expected a file name", 1),
+    )]
+    fn reports_include_errors(test: &str, files: &[(&str, &str)], message: &str, chain_len: usize) {
+        let dir = write_files(&format!("include-{}", test), files);
+        match IonReader::read_file_with(dir.join("main.ion"), &options()) {
+            Err(IonError { ty: IonErrorType::IncludeError { message: msg, chain }, .. }) => {
+                assert!(msg.contains(message), "{}", msg);
+                assert_eq!(chain.len(), chain_len, "{:?}", chain);
+            }
+            other => panic!("expected an include error, got {:?}", other),
+        }
+    }
+}
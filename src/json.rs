@@ -0,0 +1,392 @@
+use std::collections::HashMap;
+use std::path::Path;
+use nom::IResult;
+use nom::branch::alt;
+use nom::bytes::complete::tag;
+use nom::character::complete::{char, digit1, multispace0, one_of};
+use nom::combinator::{cut, map, opt, recognize};
+use nom::error::{context, VerboseError, VerboseErrorKind};
+use nom::sequence::{delimited, pair, preceded, terminated, tuple};
+use crate::error::{IonError, IonResult};
+use crate::types::{Annotations, IonList, IonStruct, IonValue, Symbol};
+
+/// Key holding a value's annotations when they are encoded with `JsonAnnotations::Encode`.
+pub const ANNOTATIONS_KEY: &str = "$annotations";
+/// Key holding the annotated value itself when annotations are encoded with `JsonAnnotations::Encode`.
+pub const VALUE_KEY: &str = "$value";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// How annotations are handled when down-converting to JSON.
+pub enum JsonAnnotations {
+    /// Annotations are dropped, as the Ion spec's down-conversion rules prescribe.
+    Drop,
+    /// Annotated values are wrapped in `{"$annotations": [...], "$value": ...}`.
+    /// `IonJson::read_string` turns these wrappers back into annotations.
+    Encode,
+}
+
+#[derive(Debug, Clone)]
+/// Options for down-converting Ion to JSON.
+pub struct JsonOptions {
+    pub annotations: JsonAnnotations,
+    /// Spread containers over multiple indented lines instead of writing everything on one line.
+    pub pretty: bool,
+}
+impl Default for JsonOptions {
+    fn default() -> Self {
+        JsonOptions { annotations: JsonAnnotations::Drop, pretty: false }
+    }
+}
+
+/// Converts between `IonValue`s and JSON text.
+///
+/// Down-conversion follows the Ion spec: timestamps become RFC 3339 strings, symbols become
+/// strings, blobs become base64 strings, `nan` and infinities become `null`, and struct fields
+/// are written in sorted order so output is stable. Up-conversion reads integral JSON numbers
+/// that fit in an `i64` as integers and every other number as a float.
+pub struct IonJson;
+impl IonJson {
+    /// Down-converts the given value to JSON text.
+    pub fn write_string(value: &IonValue, options: &JsonOptions) -> String {
+        let mut out = String::new();
+        write_value(&mut out, value, options, 0);
+        out
+    }
+
+    /// Reads a JSON file into an `IonValue`.
+    pub fn read_file(path: impl AsRef<Path>) -> IonResult<IonValue> {
+        let s = std::fs::read_to_string(path.as_ref())?;
+        IonJson::read_string(&s)
+    }
+
+    /// Up-converts a single JSON value into an `IonValue`.
+    pub fn read_string(text: &str) -> IonResult<IonValue> {
+        match delimited(multispace0, json_value, multispace0)(text) {
+            Ok(("", value)) => Ok(value),
+            Ok((rest, _)) => Err(IonError::syntax(text, text.len() - rest.len(), "unexpected trailing characters")),
+            Err(e) => Err(convert_error(text, e)),
+        }
+    }
+}
+
+fn newline(out: &mut String, options: &JsonOptions, depth: usize) {
+    if options.pretty {
+        out.push('\n');
+        out.push_str(&"  ".repeat(depth));
+    }
+}
+
+fn write_value(out: &mut String, value: &IonValue, options: &JsonOptions, depth: usize) {
+    if options.annotations == JsonAnnotations::Encode && !value.annotations().is_empty() {
+        let separator = if options.pretty { ": " } else { ":" };
+        out.push('{');
+        newline(out, options, depth + 1);
+        write_string(out, ANNOTATIONS_KEY);
+        out.push_str(separator);
+        out.push('[');
+        for (i, ann) in value.annotations().iter().enumerate() {
+            if i > 0 { out.push(','); }
+            write_string(out, ann);
+        }
+        out.push_str("],");
+        newline(out, options, depth + 1);
+        write_string(out, VALUE_KEY);
+        out.push_str(separator);
+        write_bare_value(out, value, options, depth + 1);
+        newline(out, options, depth);
+        out.push('}');
+    }
+    else {
+        write_bare_value(out, value, options, depth);
+    }
+}
+
+fn write_bare_value(out: &mut String, value: &IonValue, options: &JsonOptions, depth: usize) {
+    match value {
+        IonValue::Null(_) => out.push_str("null"),
+        IonValue::Boolean(b, _) => out.push_str(if *b { "true" } else { "false" }),
+        IonValue::Integer(i, _) => out.push_str(&i.to_string()),
+        IonValue::Float(f, _) => {
+            if f.is_finite() { out.push_str(&format!("{:?}", f)) }
+            else { out.push_str("null") }
+        }
+        IonValue::Timestamp(ts, _) => write_string(out, &ts.to_rfc3339()),
+        IonValue::Blob(b, _) => write_string(out, &base64::encode(b)),
+        IonValue::String(s, _) => write_string(out, s),
+        IonValue::Symbol(s, _) => write_string(out, s),
+        IonValue::List(list, _) => {
+            if list.len() == 0 {
+                out.push_str("[]");
+                return;
+            }
+            out.push('[');
+            for (i, item) in list.iter().enumerate() {
+                if i > 0 { out.push(','); }
+                newline(out, options, depth + 1);
+                write_value(out, item, options, depth + 1);
+            }
+            newline(out, options, depth);
+            out.push(']');
+        }
+        IonValue::Struct(st, _) => {
+            let mut fields: Vec<_> = st.iter_fields().collect();
+            if fields.is_empty() {
+                out.push_str("{}");
+                return;
+            }
+            fields.sort_by(|a, b| a.0.cmp(b.0));
+            out.push('{');
+            for (i, (name, field)) in fields.into_iter().enumerate() {
+                if i > 0 { out.push(','); }
+                newline(out, options, depth + 1);
+                write_string(out, name);
+                out.push_str(if options.pretty { ": " } else { ":" });
+                write_value(out, field, options, depth + 1);
+            }
+            newline(out, options, depth);
+            out.push('}');
+        }
+    }
+}
+
+fn write_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+type ParseResult<'a, T> = IResult<&'a str, T, VerboseError<&'a str>>;
+
+fn failure<'a, T>(input: &'a str, message: &'static str) -> ParseResult<'a, T> {
+    Err(nom::Err::Failure(VerboseError { errors: vec![(input, VerboseErrorKind::Context(message))] }))
+}
+
+fn convert_error(source: &str, e: nom::Err<VerboseError<&str>>) -> IonError {
+    let e = match e {
+        nom::Err::Incomplete(_) => return IonError::syntax(source, source.len(), "unexpected end of input"),
+        nom::Err::Error(e) | nom::Err::Failure(e) => e,
+    };
+    let offset = e.errors.first().map(|(at, _)| source.len() - at.len()).unwrap_or(0);
+    let message = e.errors.iter()
+        .find_map(|(_, kind)| match kind {
+            VerboseErrorKind::Context(msg) => Some(msg.to_string()),
+            _ => None,
+        })
+        .unwrap_or_else(|| "invalid JSON".to_string());
+    IonError::syntax(source, offset, message)
+}
+
+fn json_value(input: &str) -> ParseResult<'_, IonValue> {
+    context("expected a JSON value", alt((
+        json_object,
+        map(json_array, |items| IonValue::List(IonList { items }, Annotations::new())),
+        map(json_string, |s| IonValue::String(s, Annotations::new())),
+        json_number,
+        map(tag("true"), |_| IonValue::Boolean(true, Annotations::new())),
+        map(tag("false"), |_| IonValue::Boolean(false, Annotations::new())),
+        map(tag("null"), |_| IonValue::Null(Annotations::new())),
+    )))(input)
+}
+
+/// Parses zero or more comma-separated `element`s. Anything following a comma must be an element.
+fn comma_separated<'a, O>(mut element: impl FnMut(&'a str) -> ParseResult<'a, O>)
+    -> impl FnMut(&'a str) -> ParseResult<'a, Vec<O>>
+{
+    move |input: &'a str| {
+        let (mut rest, first) = match element(input) {
+            Ok(result) => result,
+            Err(nom::Err::Error(_)) => return Ok((input, Vec::new())),
+            Err(e) => return Err(e),
+        };
+        let mut items = vec![first];
+        while let Some(after_comma) = rest.strip_prefix(',') {
+            let (after, item) = cut(&mut element)(after_comma)?;
+            items.push(item);
+            rest = after;
+        }
+        Ok((rest, items))
+    }
+}
+
+fn json_array(input: &str) -> ParseResult<'_, Vec<IonValue>> {
+    preceded(char('['), cut(terminated(
+        comma_separated(delimited(multispace0, json_value, multispace0)),
+        context("expected `,` or `]`", preceded(multispace0, char(']'))),
+    )))(input)
+}
+
+fn json_object(input: &str) -> ParseResult<'_, IonValue> {
+    let member = tuple((
+        preceded(multispace0, json_string),
+        cut(context("expected `:`", delimited(multispace0, char(':'), multispace0))),
+        cut(terminated(json_value, multispace0)),
+    ));
+    let (rest, members) = preceded(char('{'), cut(terminated(
+        comma_separated(map(member, |(name, _, value)| (name, value))),
+        context("expected `,` or `}`", preceded(multispace0, char('}'))),
+    )))(input)?;
+    let mut fields: HashMap<Symbol, IonValue> = members.into_iter().map(|(name, value)| (Symbol::from(name), value)).collect();
+
+    // turn `{"$annotations": [...], "$value": ...}` wrappers back into annotated values
+    if fields.len() == 2 && fields.contains_key(VALUE_KEY) {
+        let annotations = fields.get(ANNOTATIONS_KEY)
+            .and_then(IonValue::as_list)
+            .and_then(|list| list.iter().map(|v| v.as_str().map(str::to_string)).collect::<Option<Annotations>>());
+        if let Some(annotations) = annotations {
+            let mut value = fields.remove(VALUE_KEY).unwrap();
+            *value.annotations_mut() = annotations;
+            return Ok((rest, value));
+        }
+    }
+    Ok((rest, IonValue::Struct(IonStruct::new(fields), Annotations::new())))
+}
+
+fn json_number(input: &str) -> ParseResult<'_, IonValue> {
+    let (rest, text) = recognize(tuple((
+        opt(char('-')),
+        alt((tag("0"), digit1)),
+        opt(pair(char('.'), digit1)),
+        opt(tuple((one_of("eE"), opt(one_of("+-")), digit1))),
+    )))(input)?;
+    let is_integral = !text.contains(&['.', 'e', 'E'][..]);
+    let value = match text.parse::<i64>() {
+        Ok(i) if is_integral => IonValue::Integer(i, Annotations::new()),
+        // the recognized text is always a valid float
+        _ => IonValue::Float(text.parse().unwrap(), Annotations::new()),
+    };
+    Ok((rest, value))
+}
+
+fn json_string(input: &str) -> ParseResult<'_, String> {
+    let (mut rest, _) = char('"')(input)?;
+    let mut out = String::new();
+    loop {
+        let mut chars = rest.chars();
+        match chars.next() {
+            None => return failure(rest, "unterminated string"),
+            Some('"') => return Ok((chars.as_str(), out)),
+            Some('\\') => {
+                let escaped = match chars.next() {
+                    Some('"') => '"',
+                    Some('\\') => '\\',
+                    Some('/') => '/',
+                    Some('b') => '\u{8}',
+                    Some('f') => '\u{c}',
+                    Some('n') => '\n',
+                    Some('r') => '\r',
+                    Some('t') => '\t',
+                    Some('u') => {
+                        let (after, c) = unicode_escape(chars.as_str())?;
+                        chars = after.chars();
+                        c
+                    }
+                    _ => return failure(rest, "invalid escape sequence"),
+                };
+                out.push(escaped);
+            }
+            Some(c) if (c as u32) < 0x20 => return failure(rest, "control character in string"),
+            Some(c) => out.push(c),
+        }
+        rest = chars.as_str();
+    }
+}
+
+/// Parses the hex digits of a `\u` escape, including the low half of a surrogate pair.
+fn unicode_escape(input: &str) -> ParseResult<'_, char> {
+    fn hex4(input: &str) -> ParseResult<'_, u32> {
+        // `from_str_radix` alone would also accept a sign, as in `\u+123`
+        let digits = input.get(..4).filter(|digits| digits.chars().all(|c| c.is_ascii_hexdigit()));
+        match digits.and_then(|digits| u32::from_str_radix(digits, 16).ok()) {
+            Some(code) => Ok((&input[4..], code)),
+            None => failure(input, "invalid unicode escape"),
+        }
+    }
+    let (rest, high) = hex4(input)?;
+    if !(0xD800..0xDC00).contains(&high) {
+        return match char::from_u32(high) {
+            Some(c) => Ok((rest, c)),
+            None => failure(input, "invalid unicode escape"),
+        };
+    }
+    let (rest, low) = preceded(tag("\\u"), hex4)(rest)
+        .or_else(|_: nom::Err<VerboseError<&str>>| failure(rest, "unpaired surrogate in unicode escape"))?;
+    match char::from_u32(0x10000 + ((high - 0xD800) << 10) + (low.wrapping_sub(0xDC00))) {
+        Some(c) if (0xDC00..0xE000).contains(&low) => Ok((rest, c)),
+        _ => failure(input, "unpaired surrogate in unicode escape"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use super::*;
+
+    #[rstest(json, expected,
+        case(r#""plain""#, "plain"),
+        case(r#""tab\tquote\"slash\/""#, "tab\tquote\"slash/"),
+        case(r#""\u00e9\u00E9""#, "\u{e9}\u{e9}"),
+        case(r#""\ud83d\ude00""#, "\u{1F600}"),
+    )]
+    fn reads_escapes(json: &str, expected: &str) {
+        assert_eq!(IonJson::read_string(json).unwrap().as_str(), Some(expected));
+    }
+
+    #[rstest(json,
+        case(r#""\u+123""#),
+        case(r#""\u12g4""#),
+        case(r#""\u12""#),
+        case(r#""\ud83d""#),
+        case(r#""\ude00""#),
+        case(r#""\q""#),
+        case("\"line\nbreak\""),
+    )]
+    fn rejects_invalid_strings(json: &str) {
+        assert!(IonJson::read_string(json).is_err(), "{} should not parse", json);
+    }
+
+    #[test]
+    fn reads_numbers() {
+        let value = IonJson::read_string("[1, -2, 1.5, 1e3, 99999999999999999999]").unwrap();
+        let items = &value.as_list().unwrap().items;
+        assert_eq!(items[0], IonValue::Integer(1, Annotations::new()));
+        assert_eq!(items[1], IonValue::Integer(-2, Annotations::new()));
+        assert_eq!(items[2], IonValue::Float(1.5, Annotations::new()));
+        assert_eq!(items[3], IonValue::Float(1000.0, Annotations::new()));
+        assert!(items[4].is(crate::types::IonType::Float));
+    }
+
+    #[test]
+    fn reports_syntax_error_position() {
+        let e = IonJson::read_string("{\n  \"a\": [1,]\n}").unwrap_err();
+        match e.ty {
+            crate::error::IonErrorType::SyntaxError { line, .. } => assert_eq!(line, 2),
+            other => panic!("unexpected error {:?}", other),
+        }
+    }
+
+    #[test]
+    fn encoded_annotations_round_trip() {
+        let json = r#"{"$annotations":["sprite"],"$value":{"frames":[1,2],"name":"hero"}}"#;
+        let value = IonJson::read_string(json).unwrap();
+        assert!(value.has_annotation("sprite"));
+        let options = JsonOptions { annotations: JsonAnnotations::Encode, pretty: false };
+        assert_eq!(IonJson::write_string(&value, &options), json);
+        assert_eq!(IonJson::write_string(&value, &JsonOptions::default()), r#"{"frames":[1,2],"name":"hero"}"#);
+    }
+
+    #[test]
+    fn escapes_when_writing() {
+        let value = IonValue::String("a\"b\n\u{1}".to_string(), Annotations::new());
+        assert_eq!(IonJson::write_string(&value, &JsonOptions::default()), r#""a\"b\n\u0001""#);
+    }
+}
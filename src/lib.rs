@@ -1,12 +1,57 @@
 pub mod types;
+pub mod document;
+pub mod symbol;
+pub mod borrowed;
+pub mod cursor;
+#[cfg(feature = "mmap")]
+pub mod mapped;
 pub mod error;
 pub mod reader;
 pub mod writer;
 pub mod walker;
 pub mod traits;
+pub mod validate;
+pub mod json;
+pub mod path;
+pub mod schema;
+pub mod describe;
+pub mod query;
+pub mod printer;
+pub mod diff;
+pub mod merge;
+pub mod patch;
+mod include;
+pub mod anchors;
+pub mod prototype;
+pub mod catalog;
+pub mod watch;
+pub mod migrate;
+#[cfg(test)]
+mod test_util;
 
 pub use types::*;
+pub use document::IonDocument;
 pub use error::*;
-pub use reader::IonReader;
-pub use walker::IonWalker;
+pub use reader::{IonReader, ReadOptions};
+pub use writer::IonWriter;
+pub use walker::{IonWalker, Branches, FieldCheck};
+pub use borrowed::{IonValueRef, IonRefWalker};
+pub use cursor::IonCursor;
+#[cfg(feature = "mmap")]
+pub use mapped::IonMappedFile;
 pub use traits::*;
+pub use validate::IonValidate;
+pub use json::{IonJson, JsonOptions, JsonAnnotations};
+pub use path::{IonPath, PathSegment};
+pub use schema::{IonSchema, Violation};
+pub use describe::{IonSchemaDescribe, IonShape, FieldShape, SchemaGenerator};
+pub use query::{IonQuery, QueryMatch};
+pub use printer::{PrettyPrinter, PrettyConfig, SymbolQuoting, FieldNames};
+pub use diff::{diff, Change, ChangeKind};
+pub use merge::{merge, merge_all, MergeOptions, ListMerge, ConflictPolicy};
+pub use patch::IonPatch;
+pub use anchors::{resolve_refs, resolve_refs_shared, AnchorOptions, IonNode};
+pub use prototype::{Prototypes, PrototypeOptions};
+pub use catalog::{IonCatalog, CatalogOptions, CatalogEntry};
+pub use watch::{IonWatcher, WatchHandle};
+pub use migrate::Migrations;
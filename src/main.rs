@@ -1,7 +1,494 @@
-use electrolyte::reader::IonReader;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use electrolyte::{FieldNames, IonError, IonJson, IonQuery, IonResult, IonSchema, IonValue, JsonAnnotations, JsonOptions,
+                  PrettyConfig, SymbolQuoting};
+use electrolyte::types::{Annotations, IonList};
+use electrolyte::reader::{IonReader, ReadOptions};
+use electrolyte::writer::{IonFormat, IonWriter};
 
-pub fn main() {
-    let contents = std::fs::read_to_string("iontestdata/placeholder.sprite.ion").unwrap();
-    let data = IonReader::read_string(&contents).unwrap();
-    println!("{:?}", data);
-}
\ No newline at end of file
+/// Exit code when every input was processed successfully.
+const EXIT_OK: u8 = 0;
+/// Exit code when at least one input failed to read, parse or convert.
+const EXIT_FAILURE: u8 = 1;
+/// Exit code for invalid command-line usage (matches clap's own usage errors).
+const EXIT_USAGE: u8 = 2;
+
+/// Reported when standard input is given as more than one input, since it can only be read once.
+const STDIN_TWICE: &str = "standard input (`-`) can only be given once";
+
+/// A single input to a subcommand, either a file or standard input.
+enum Input {
+    Stdin,
+    File(PathBuf),
+}
+
+impl Input {
+    fn name(&self) -> String {
+        match self {
+            Input::Stdin => "<stdin>".to_string(),
+            Input::File(path) => path.display().to_string(),
+        }
+    }
+
+    fn read_bytes(&self) -> IonResult<Vec<u8>> {
+        match self {
+            Input::Stdin => {
+                let mut buf = Vec::new();
+                std::io::stdin().read_to_end(&mut buf)?;
+                Ok(buf)
+            }
+            Input::File(path) => Ok(std::fs::read(path)?),
+        }
+    }
+
+    /// Reads the input and returns its top-level values.
+    fn read(&self, options: &ReadOptions) -> IonResult<Vec<IonValue>> {
+        let doc = match self {
+            Input::Stdin => IonReader::read_from_with(std::io::stdin().lock(), options)?,
+            Input::File(path) => IonReader::read_file_with(path, options)?,
+        };
+        Ok(doc.into_values())
+    }
+
+    /// Reads the input as a single JSON value.
+    fn read_json(&self) -> IonResult<IonValue> {
+        let bytes = self.read_bytes()?;
+        let text = String::from_utf8_lossy(&bytes);
+        IonJson::read_string(&text)
+    }
+}
+
+/// Output encodings supported by `convert`.
+enum Output {
+    Ion(IonFormat),
+    Json(JsonOptions),
+}
+
+/// Expands the given path arguments into inputs. Arguments containing glob metacharacters are
+/// expanded here so patterns work even when the shell doesn't do it (or they were quoted).
+/// No arguments, or a lone `-`, means standard input.
+fn collect_inputs(matches: &ArgMatches) -> Result<Vec<Input>, String> {
+    let args: Vec<&String> = match matches.get_many::<String>("paths") {
+        Some(values) => values.collect(),
+        None => return Ok(vec![Input::Stdin]),
+    };
+    let mut inputs = Vec::new();
+    for arg in args {
+        if arg == "-" {
+            if inputs.iter().any(|input| matches!(input, Input::Stdin)) {
+                return Err(STDIN_TWICE.to_string());
+            }
+            inputs.push(Input::Stdin);
+        }
+        else if arg.contains(&['*', '?', '['][..]) {
+            let paths = glob::glob(arg).map_err(|e| format!("invalid pattern `{}`: {}", arg, e))?;
+            let mut matched: Vec<PathBuf> = paths.filter_map(Result::ok).filter(|p| p.is_file()).collect();
+            if matched.is_empty() {
+                return Err(format!("no files match `{}`", arg));
+            }
+            matched.sort();
+            inputs.extend(matched.into_iter().map(Input::File));
+        }
+        else {
+            inputs.push(Input::File(PathBuf::from(arg)));
+        }
+    }
+    Ok(inputs)
+}
+
+/// Turns a single path argument into an `Input`, where none or `-` means standard input.
+fn input_arg(path: Option<&String>) -> Input {
+    match path.map(String::as_str) {
+        None | Some("-") => Input::Stdin,
+        Some(path) => Input::File(PathBuf::from(path)),
+    }
+}
+
+fn report(input: &Input, error: &IonError) {
+    eprintln!("{}: {}", input.name(), error);
+}
+
+fn paths_arg() -> Arg<'static> {
+    Arg::new("paths")
+        .help("Files or glob patterns to read. Reads standard input if omitted or `-`.")
+        .takes_value(true)
+        .multiple_values(true)
+}
+
+fn dump(inputs: &[Input], options: &ReadOptions, config: &PrettyConfig) -> u8 {
+    let mut code = EXIT_OK;
+    for input in inputs {
+        let text = input.read(options).map(|values| IonWriter::write_text_with(&values, config));
+        match text {
+            Ok(text) => {
+                if inputs.len() > 1 {
+                    println!("// {}", input.name());
+                }
+                print!("{}", text);
+            }
+            Err(e) => {
+                report(input, &e);
+                code = EXIT_FAILURE;
+            }
+        }
+    }
+    code
+}
+
+/// Checks that every input parses and, if a schema type is given, that every top-level value
+/// in it is valid for that type. Every input is reported; a schema error (such as an unknown
+/// type) exits with `EXIT_USAGE` once all inputs have been checked.
+fn validate(inputs: &[Input], options: &ReadOptions, schema: Option<(&IonSchema, &str)>, quiet: bool) -> u8 {
+    let mut failed = 0;
+    let mut schema_error = false;
+    for input in inputs {
+        let values = match input.read(options) {
+            Ok(values) => values,
+            Err(e) => {
+                report(input, &e);
+                failed += 1;
+                continue;
+            }
+        };
+        let mut violations = Vec::new();
+        if let Some((schema, type_name)) = schema {
+            let checked = values.iter().enumerate().try_for_each(|(i, value)| {
+                let found = schema.validate(type_name, value)?;
+                violations.extend(found.into_iter().map(|v| (i, v)));
+                Ok::<_, IonError>(())
+            });
+            if let Err(e) = checked {
+                report(input, &e);
+                schema_error = true;
+                failed += 1;
+                continue;
+            }
+        }
+        if violations.is_empty() {
+            if !quiet { println!("ok: {}", input.name()) }
+        }
+        else {
+            for (i, violation) in violations {
+                eprintln!("{}: value {}: {}", input.name(), i, violation);
+            }
+            failed += 1;
+        }
+    }
+    if !quiet {
+        println!("{} of {} inputs valid", inputs.len() - failed, inputs.len());
+    }
+    if schema_error { EXIT_USAGE } else if failed == 0 { EXIT_OK } else { EXIT_FAILURE }
+}
+
+fn count(inputs: &[Input], options: &ReadOptions) -> u8 {
+    let mut code = EXIT_OK;
+    let mut total = 0;
+    for input in inputs {
+        match input.read(options) {
+            Ok(values) => {
+                println!("{}\t{}", values.len(), input.name());
+                total += values.len();
+            }
+            Err(e) => {
+                report(input, &e);
+                code = EXIT_FAILURE;
+            }
+        }
+    }
+    if inputs.len() > 1 {
+        println!("{}\ttotal", total);
+    }
+    code
+}
+
+/// Prints every value matching `query`, exiting with 1 if nothing matched (like `grep`).
+/// Inputs with several top-level values are queried as a list, so paths start with an index.
+fn query(inputs: &[Input], options: &ReadOptions, query: &IonQuery) -> u8 {
+    let mut code = EXIT_OK;
+    let mut matched = false;
+    for input in inputs {
+        let mut values = match input.read(options) {
+            Ok(values) => values,
+            Err(e) => {
+                report(input, &e);
+                code = EXIT_FAILURE;
+                continue;
+            }
+        };
+        let root = match values.len() {
+            1 => values.remove(0),
+            _ => IonValue::List(IonList { items: values }, Annotations::new()),
+        };
+        for m in query.run(&root) {
+            if inputs.len() > 1 {
+                print!("{}: ", input.name());
+            }
+            println!("{} = {}", m.path, m.value);
+            matched = true;
+        }
+    }
+    if code == EXIT_OK && !matched { EXIT_FAILURE } else { code }
+}
+
+/// Like `diff(1)`: exits with 0 if the documents are equal, 1 if they differ and 2 on errors.
+/// Documents with several top-level values are compared as lists.
+fn diff(old: &Input, new: &Input, options: &ReadOptions, quiet: bool) -> u8 {
+    let mut docs = Vec::new();
+    for input in [old, new] {
+        match input.read(options) {
+            Ok(values) => docs.push(values),
+            Err(e) => {
+                report(input, &e);
+                return EXIT_USAGE;
+            }
+        }
+    }
+    let new_values = docs.pop().unwrap();
+    let old_values = docs.pop().unwrap();
+    let (old_doc, new_doc) = match (old_values.len(), new_values.len()) {
+        (1, 1) => (old_values.into_iter().next().unwrap(), new_values.into_iter().next().unwrap()),
+        _ => (IonValue::List(IonList { items: old_values }, Annotations::new()),
+              IonValue::List(IonList { items: new_values }, Annotations::new())),
+    };
+    let changes = electrolyte::diff(&old_doc, &new_doc);
+    if !quiet {
+        print!("{}", electrolyte::diff::render(&changes));
+    }
+    if changes.is_empty() { EXIT_OK } else { EXIT_FAILURE }
+}
+
+fn convert(input: &Input, options: &ReadOptions, from_json: bool, to: &Output, output: Option<&String>) -> u8 {
+    let values = if from_json { input.read_json().map(|value| vec![value]) } else { input.read(options) };
+    let bytes = values.and_then(|values| match to {
+        Output::Ion(IonFormat::Text) => Ok(IonWriter::write_text(&values).into_bytes()),
+        Output::Ion(IonFormat::Binary) => IonWriter::write_binary(&values),
+        // one JSON document per top-level value
+        Output::Json(options) => Ok(values.iter()
+            .map(|value| IonJson::write_string(value, options) + "\n")
+            .collect::<String>()
+            .into_bytes()),
+    });
+    let bytes = match bytes {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            report(input, &e);
+            return EXIT_FAILURE;
+        }
+    };
+    let written = match output {
+        Some(path) => std::fs::write(path, &bytes),
+        None => std::io::stdout().write_all(&bytes),
+    };
+    match written {
+        Ok(()) => EXIT_OK,
+        Err(e) => {
+            eprintln!("{}: {}", output.map(String::as_str).unwrap_or("<stdout>"), e);
+            EXIT_FAILURE
+        }
+    }
+}
+
+pub fn main() -> ExitCode {
+    let matches = Command::new("electrolyte")
+        .about("Inspect, validate and convert Amazon Ion files")
+        .subcommand_required(true)
+        .arg_required_else_help(true)
+        .arg(Arg::new("includes")
+            .long("includes")
+            .global(true)
+            .action(ArgAction::SetTrue)
+            .help("Resolve include::\"file.ion\" directives in inputs"))
+        .arg(Arg::new("include-dir")
+            .short('I')
+            .long("include-dir")
+            .global(true)
+            .takes_value(true)
+            .multiple_occurrences(true)
+            .help("Directory to search for included files (implies --includes)"))
+        .arg(Arg::new("refs")
+            .long("refs")
+            .global(true)
+            .action(ArgAction::SetTrue)
+            .help("Replace ref::name references with the value anchored as anchor::name::..."))
+        .subcommand(Command::new("dump")
+            .about("Pretty-print Ion files")
+            .arg(Arg::new("indent")
+                .long("indent")
+                .takes_value(true)
+                .default_value("2")
+                .value_parser(clap::value_parser!(usize))
+                .help("Spaces per indentation level"))
+            .arg(Arg::new("width")
+                .long("width")
+                .takes_value(true)
+                .default_value("80")
+                .value_parser(clap::value_parser!(usize))
+                .help("Line width to keep containers within where possible"))
+            .arg(Arg::new("compact")
+                .long("compact")
+                .action(ArgAction::SetTrue)
+                .help("Write each top-level value on a single line"))
+            .arg(Arg::new("sort-keys")
+                .long("sort-keys")
+                .action(ArgAction::SetTrue)
+                .help("Write struct fields sorted by name"))
+            .arg(Arg::new("quote-symbols")
+                .long("quote-symbols")
+                .action(ArgAction::SetTrue)
+                .help("Quote every symbol and annotation, not just the ones that need it"))
+            .arg(Arg::new("string-keys")
+                .long("string-keys")
+                .action(ArgAction::SetTrue)
+                .help("Write struct field names as strings"))
+            .arg(paths_arg()))
+        .subcommand(Command::new("validate")
+            .about("Check that Ion files parse and optionally match a schema type, exiting with 1 if any fail")
+            .arg(Arg::new("quiet")
+                .short('q')
+                .long("quiet")
+                .action(ArgAction::SetTrue)
+                .help("Only print errors"))
+            .arg(Arg::new("schema")
+                .long("schema")
+                .takes_value(true)
+                .requires("type")
+                .help("Ion Schema file to validate against"))
+            .arg(Arg::new("type")
+                .long("type")
+                .takes_value(true)
+                .requires("schema")
+                .help("Schema type every top-level value must match"))
+            .arg(paths_arg()))
+        .subcommand(Command::new("count")
+            .about("Count the top-level values in Ion files")
+            .arg(paths_arg()))
+        .subcommand(Command::new("query")
+            .about("Print the values matching a path expression like `animations[*].frames[0].duration`")
+            .arg(Arg::new("expr").required(true).help("Path expression to evaluate"))
+            .arg(paths_arg()))
+        .subcommand(Command::new("diff")
+            .about("Compare two Ion files structurally, exiting with 1 if they differ")
+            .arg(Arg::new("quiet")
+                .short('q')
+                .long("quiet")
+                .action(ArgAction::SetTrue)
+                .help("Only set the exit code"))
+            .arg(Arg::new("old").required(true).help("Original file, or `-` for standard input"))
+            .arg(Arg::new("new").required(true).help("Changed file, or `-` for standard input if `old` isn't")))
+        .subcommand(Command::new("convert")
+            .about("Convert an Ion file between text, binary and JSON encodings")
+            .arg(Arg::new("to")
+                .long("to")
+                .takes_value(true)
+                .required(true)
+                .value_parser(["text", "binary", "json"])
+                .help("Output encoding. JSON output has one document per top-level value."))
+            .arg(Arg::new("from")
+                .long("from")
+                .takes_value(true)
+                .default_value("ion")
+                .value_parser(["ion", "json"])
+                .help("Input encoding. Ion input may be text or binary."))
+            .arg(Arg::new("pretty")
+                .long("pretty")
+                .action(ArgAction::SetTrue)
+                .help("Indent JSON output"))
+            .arg(Arg::new("encode-annotations")
+                .long("encode-annotations")
+                .action(ArgAction::SetTrue)
+                .help("Keep annotations in JSON output as {\"$annotations\": [...], \"$value\": ...} wrappers"))
+            .arg(Arg::new("output")
+                .short('o')
+                .long("output")
+                .takes_value(true)
+                .help("File to write to. Writes to standard output if omitted."))
+            .arg(Arg::new("input").help("File to read. Reads standard input if omitted or `-`.")))
+        .get_matches();
+
+    let (name, sub) = matches.subcommand().expect("subcommand is required");
+    let search_path: Vec<PathBuf> = sub.get_many::<String>("include-dir")
+        .map(|dirs| dirs.map(PathBuf::from).collect())
+        .unwrap_or_default();
+    let options = ReadOptions {
+        includes: sub.get_flag("includes") || !search_path.is_empty(),
+        search_path,
+        references: sub.get_flag("refs"),
+        ..Default::default()
+    };
+    let code = match name {
+        "diff" => {
+            let old = input_arg(sub.get_one::<String>("old"));
+            let new = input_arg(sub.get_one::<String>("new"));
+            if let (Input::Stdin, Input::Stdin) = (&old, &new) {
+                eprintln!("error: {}", STDIN_TWICE);
+                return ExitCode::from(EXIT_USAGE);
+            }
+            diff(&old, &new, &options, sub.get_flag("quiet"))
+        }
+        "convert" => {
+            let input = input_arg(sub.get_one::<String>("input"));
+            let to = match sub.get_one::<String>("to").map(String::as_str) {
+                Some("binary") => Output::Ion(IonFormat::Binary),
+                Some("json") => Output::Json(JsonOptions {
+                    annotations: if sub.get_flag("encode-annotations") { JsonAnnotations::Encode }
+                                 else { JsonAnnotations::Drop },
+                    pretty: sub.get_flag("pretty"),
+                }),
+                _ => Output::Ion(IonFormat::Text),
+            };
+            let from_json = sub.get_one::<String>("from").map(String::as_str) == Some("json");
+            convert(&input, &options, from_json, &to, sub.get_one::<String>("output"))
+        }
+        _ => {
+            let inputs = match collect_inputs(sub) {
+                Ok(inputs) => inputs,
+                Err(msg) => {
+                    eprintln!("error: {}", msg);
+                    return ExitCode::from(EXIT_USAGE);
+                }
+            };
+            match name {
+                "dump" => {
+                    let config = PrettyConfig {
+                        indent: *sub.get_one::<usize>("indent").expect("indent has a default"),
+                        max_width: *sub.get_one::<usize>("width").expect("width has a default"),
+                        compact: sub.get_flag("compact"),
+                        sort_keys: sub.get_flag("sort-keys"),
+                        symbol_quoting: if sub.get_flag("quote-symbols") { SymbolQuoting::Always }
+                                        else { SymbolQuoting::Minimal },
+                        field_names: if sub.get_flag("string-keys") { FieldNames::Strings }
+                                     else { FieldNames::Symbols },
+                    };
+                    dump(&inputs, &options, &config)
+                }
+                "validate" => {
+                    let schema = match sub.get_one::<String>("schema").map(IonSchema::read_file) {
+                        Some(Ok(schema)) => Some(schema),
+                        Some(Err(e)) => {
+                            eprintln!("{}: {}", sub.get_one::<String>("schema").unwrap(), e);
+                            return ExitCode::from(EXIT_USAGE);
+                        }
+                        None => None,
+                    };
+                    let type_name = sub.get_one::<String>("type").map(String::as_str).unwrap_or_default();
+                    validate(&inputs, &options, schema.as_ref().map(|s| (s, type_name)), sub.get_flag("quiet"))
+                }
+                "count" => count(&inputs, &options),
+                "query" => {
+                    let expr = sub.get_one::<String>("expr").expect("expr is required");
+                    match IonQuery::parse(expr) {
+                        Ok(q) => query(&inputs, &options, &q),
+                        Err(e) => {
+                            eprintln!("{}: {}", expr, e);
+                            EXIT_USAGE
+                        }
+                    }
+                }
+                _ => unreachable!(),
+            }
+        }
+    };
+    ExitCode::from(code)
+}
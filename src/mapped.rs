@@ -0,0 +1,90 @@
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use memmap::Mmap;
+use crate::borrowed::IonValueRef;
+use crate::cursor::IonCursor;
+use crate::document::IonDocument;
+use crate::error::IonResult;
+use crate::reader::IonReader;
+
+/// An Ion file mapped into memory, for reading large binary asset packs without first copying
+/// them onto the heap. Use `cursor` to only decode the values that are needed.
+///
+/// Needs the `mmap` feature.
+pub struct IonMappedFile {
+    path: PathBuf,
+    map: Mmap,
+}
+
+impl IonMappedFile {
+    /// Maps the file at `path`.
+    ///
+    /// The file mustn't be modified or truncated while it's mapped, or reads may see the change
+    /// (or fault); asset packs are expected to be written once and then only read.
+    pub fn open(path: impl AsRef<Path>) -> IonResult<Self> {
+        let path = path.as_ref();
+        let file = File::open(path)?;
+        // SAFETY: see above; the mapping is only ever read through `&[u8]`.
+        let map = unsafe { Mmap::map(&file)? };
+        Ok(IonMappedFile { path: path.to_path_buf(), map })
+    }
+
+    /// Returns the path the file was opened from.
+    pub fn path(&self) -> &Path { &self.path }
+
+    /// Returns the mapped contents.
+    pub fn as_bytes(&self) -> &[u8] { &self.map }
+
+    /// Returns a cursor positioned before the first top-level value.
+    pub fn cursor(&self) -> IonResult<IonCursor<'_>> {
+        IonCursor::new(&self.map)
+    }
+
+    /// Reads every top-level value.
+    pub fn read(&self) -> IonResult<IonDocument> {
+        IonReader::read_buffer(&self.map)
+    }
+
+    /// Reads every top-level value, borrowing strings from the mapping where possible.
+    pub fn read_borrowed(&self) -> IonResult<Vec<IonValueRef<'_>>> {
+        IonReader::read_borrowed(&self.map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::IonType;
+    use crate::writer::{IonFormat, IonWriter};
+    use crate::test_util::{value, write_files};
+
+    #[test]
+    fn reads_mapped_binary_files() {
+        let dir = write_files("mapped", &[]);
+        let path = dir.join("assets.10n");
+        let values = vec![
+            value(r#"texture::{ name: "hero", size: 64 }"#),
+            value(r#"sound::{ name: "step" }"#),
+            value(r#"texture::{ name: "tiles", size: 256 }"#),
+        ];
+        IonWriter::write_file(&path, &values, IonFormat::Binary).unwrap();
+        let file = IonMappedFile::open(&path).unwrap();
+        assert_eq!(file.path(), path);
+        assert_eq!(file.as_bytes(), std::fs::read(&path).unwrap());
+
+        let mut cursor = file.cursor().unwrap();
+        let mut names = Vec::new();
+        while let Some(ty) = cursor.next_value().unwrap() {
+            assert_eq!(ty, IonType::Struct);
+            if cursor.annotations().unwrap().contains("texture") {
+                cursor.step_in().unwrap();
+                assert!(cursor.seek_field("name").unwrap());
+                names.push(cursor.read_value().unwrap());
+                cursor.step_out().unwrap();
+            }
+        }
+        assert_eq!(names, vec![value(r#""hero""#), value(r#""tiles""#)]);
+
+        assert_eq!(file.read().unwrap().into_values(), values);
+    }
+}
@@ -0,0 +1,188 @@
+//! Deep merging of layered documents, e.g. a base config followed by per-platform and per-user
+//! overrides.
+//!
+//! Structs are merged field by field. Any other value in a later layer replaces the earlier one,
+//! except lists, which can also be appended (see `ListMerge`). A field annotated with the
+//! delete marker (`delete::` by default) removes that field instead:
+//!
+//! ```ion
+//! // base.ion                      // user.ion
+//! { window: { width: 1280,         { window: { width: 1920,
+//!             vsync: true },                   vsync: delete::null } }
+//! ```
+//!
+//! merges to `{ window: { width: 1920 } }`.
+
+use crate::error::{IonError, IonErrorType, IonResult};
+use crate::path::IonPath;
+use crate::types::{Annotations, IonList, IonStruct, IonValue};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// How a list in a later layer combines with a list in an earlier one.
+pub enum ListMerge {
+    /// The later list replaces the earlier one.
+    Replace,
+    /// The later list's items are appended to the earlier one.
+    Append,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// What to do when a later layer overrides an existing value.
+pub enum ConflictPolicy {
+    /// The later value wins, even if it has a different type.
+    Override,
+    /// The later value wins if it has the same type; a different type is a `MergeConflict` error.
+    ErrorOnTypeMismatch,
+    /// Any value other than a struct or appended list which is present in both layers with a
+    /// different value is a `MergeConflict` error.
+    /// Useful when merging fragments which are supposed to be independent.
+    ErrorOnChange,
+}
+
+#[derive(Debug, Clone)]
+/// Options for `merge` and `merge_all`.
+pub struct MergeOptions {
+    pub lists: ListMerge,
+    pub conflicts: ConflictPolicy,
+    /// Fields annotated with this are removed instead of merged. `None` disables delete markers.
+    pub delete_annotation: Option<String>,
+}
+
+impl Default for MergeOptions {
+    fn default() -> Self {
+        MergeOptions {
+            lists: ListMerge::Replace,
+            conflicts: ConflictPolicy::Override,
+            delete_annotation: Some("delete".to_string()),
+        }
+    }
+}
+
+/// Merges `overlay` on top of `base`, returning the merged document.
+pub fn merge(base: &IonValue, overlay: &IonValue, options: &MergeOptions) -> IonResult<IonValue> {
+    let mut merged = base.clone();
+    merge_into(&mut merged, overlay, options)?;
+    Ok(merged)
+}
+
+/// Merges every layer on top of the previous ones, in order.
+/// Returns an empty struct if there are no layers.
+pub fn merge_all(layers: &[IonValue], options: &MergeOptions) -> IonResult<IonValue> {
+    let mut layers = layers.iter();
+    let mut merged = match layers.next() {
+        Some(first) => strip_deletes(first, options),
+        None => return Ok(IonValue::Struct(IonStruct::new_empty(), Annotations::new())),
+    };
+    for layer in layers {
+        merge_into(&mut merged, layer, options)?;
+    }
+    Ok(merged)
+}
+
+/// Merges `overlay` into `base` in place.
+pub fn merge_into(base: &mut IonValue, overlay: &IonValue, options: &MergeOptions) -> IonResult<()> {
+    merge_value(&IonPath::root(), base, overlay, options)
+}
+
+fn is_delete(value: &IonValue, options: &MergeOptions) -> bool {
+    options.delete_annotation.as_ref().is_some_and(|ann| value.has_annotation(ann))
+}
+
+/// Returns a copy of `value` without any fields marked for deletion, since there's nothing
+/// underneath for them to delete.
+pub(crate) fn strip_deletes(value: &IonValue, options: &MergeOptions) -> IonValue {
+    match value {
+        IonValue::Struct(st, ann) if options.delete_annotation.is_some() => {
+            let mut stripped = IonStruct::new_empty();
+            for (name, field) in st.iter_fields().filter(|(_, field)| !is_delete(field, options)) {
+                stripped.insert(name.clone(), strip_deletes(field, options));
+            }
+            IonValue::Struct(stripped, ann.clone())
+        }
+        IonValue::List(list, ann) if options.delete_annotation.is_some() => {
+            let items = list.iter().map(|item| strip_deletes(item, options)).collect();
+            IonValue::List(IonList { items }, ann.clone())
+        }
+        _ => value.clone(),
+    }
+}
+
+fn conflict(path: &IonPath, message: String) -> IonError {
+    IonError::new(IonErrorType::MergeConflict(message), path.to_scopes())
+}
+
+fn merge_value(path: &IonPath, base: &mut IonValue, overlay: &IonValue, options: &MergeOptions) -> IonResult<()> {
+    // an unannotated container keeps the annotations it already had
+    let annotations = if overlay.annotations().is_empty() { base.annotations().clone() }
+                      else { overlay.annotations().clone() };
+    match (&mut *base, overlay) {
+        (IonValue::Struct(st, _), IonValue::Struct(over, _)) => {
+            for (name, field) in over.iter_fields() {
+                if is_delete(field, options) {
+                    st.remove(name);
+                    continue;
+                }
+                match st.field_mut(name) {
+                    Some(existing) => merge_value(&path.field(name), existing, field, options)?,
+                    None => { st.insert(name.clone(), strip_deletes(field, options)); }
+                }
+            }
+            *base.annotations_mut() = annotations;
+        }
+        (IonValue::List(list, _), IonValue::List(over, _)) if options.lists == ListMerge::Append => {
+            list.items.extend(over.iter().map(|item| strip_deletes(item, options)));
+            *base.annotations_mut() = annotations;
+        }
+        _ => {
+            if base.ty() != overlay.ty() && options.conflicts != ConflictPolicy::Override {
+                return Err(conflict(path, format!("can't merge {:?} into {:?}", overlay.ty(), base.ty())));
+            }
+            if options.conflicts == ConflictPolicy::ErrorOnChange && !crate::diff::same(base, overlay) {
+                return Err(conflict(path, format!("{} would be replaced with {}", base, overlay)));
+            }
+            *base = strip_deletes(overlay, options);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::value;
+
+    #[test]
+    fn merges_structs_deeply() {
+        let base = value("{ window: { width: 1280, vsync: true }, title: game }");
+        let user = value("{ window: { width: 1920, vsync: delete::null }, volume: 3 }");
+        let merged = merge(&base, &user, &MergeOptions::default()).unwrap();
+        assert_eq!(merged, value("{ window: { width: 1920 }, title: game, volume: 3 }"));
+    }
+
+    #[test]
+    fn merges_lists() {
+        let (base, overlay) = (value("{ l: [1, 2] }"), value("{ l: [3] }"));
+        assert_eq!(merge(&base, &overlay, &MergeOptions::default()).unwrap(), value("{ l: [3] }"));
+        let append = MergeOptions { lists: ListMerge::Append, ..MergeOptions::default() };
+        assert_eq!(merge(&base, &overlay, &append).unwrap(), value("{ l: [1, 2, 3] }"));
+    }
+
+    #[test]
+    fn merges_layers_in_order() {
+        let layers = [value("{ a: 1, b: 1 }"), value("{ b: 2, c: 2 }"), value("{ c: 3 }")];
+        assert_eq!(merge_all(&layers, &MergeOptions::default()).unwrap(), value("{ a: 1, b: 2, c: 3 }"));
+    }
+
+    #[test]
+    fn reports_conflicts() {
+        let strict = MergeOptions { conflicts: ConflictPolicy::ErrorOnTypeMismatch, ..MergeOptions::default() };
+        let e = merge(&value("{ a: { b: 1 } }"), &value("{ a: { b: \"1\" } }"), &strict).unwrap_err();
+        assert!(matches!(e.ty, IonErrorType::MergeConflict(_)));
+        assert_eq!(e.path(), "a.b");
+        assert!(merge(&value("{ a: 1 }"), &value("{ a: 2 }"), &strict).is_ok());
+
+        let unchanged = MergeOptions { conflicts: ConflictPolicy::ErrorOnChange, ..MergeOptions::default() };
+        assert!(merge(&value("{ a: 1 }"), &value("{ a: 2 }"), &unchanged).is_err());
+        assert!(merge(&value("{ a: 1 }"), &value("{ a: 1, b: 2 }"), &unchanged).is_ok());
+    }
+}
@@ -0,0 +1,304 @@
+use std::collections::BTreeMap;
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+use crate::document::IonDocument;
+use crate::error::{IonError, IonErrorType, IonResult};
+use crate::types::{Annotations, IonValue, Symbol};
+
+type MigrationFn = dyn Fn(IonValue) -> IonResult<IonValue> + Send + Sync;
+
+#[derive(Clone)]
+struct Step {
+    to: u32,
+    migrate: Arc<MigrationFn>,
+}
+
+#[derive(Clone)]
+/// Upgrades documents written by older releases to the current format, one registered step at
+/// a time, so old save files and assets keep loading.
+///
+/// A document declares its version on its first value, with an annotation like `v3::{...}` or
+/// a `version: 3` field, and every value in the document is migrated from that version. Once
+/// migrated, the declaration is updated to the current version.
+///
+/// ```ignore
+/// let migrations = Migrations::new(3)
+///     .step(1, 2, |mut value| { rename_field(&mut value, "speed", "velocity"); Ok(value) })
+///     .step(2, 3, add_default_layer);
+/// let options = ReadOptions { migrations: Some(migrations), ..ReadOptions::default() };
+/// ```
+pub struct Migrations {
+    current: u32,
+    steps: BTreeMap<u32, Step>,
+    version_field: String,
+    annotation_prefix: String,
+    unversioned: Option<u32>,
+}
+
+impl Migrations {
+    /// Create an empty chain for documents whose newest version is `current`.
+    pub fn new(current: u32) -> Self {
+        Migrations {
+            current,
+            steps: BTreeMap::new(),
+            version_field: "version".to_string(),
+            annotation_prefix: "v".to_string(),
+            unversioned: None,
+        }
+    }
+
+    /// Registers a step which turns a version `from` document into a version `to` one. Steps may
+    /// skip versions; registering a second step from the same version replaces the first.
+    pub fn step<F>(mut self, from: u32, to: u32, migrate: F) -> Self
+        where F: Fn(IonValue) -> IonResult<IonValue> + Send + Sync + 'static
+    {
+        self.steps.insert(from, Step { to, migrate: Arc::new(migrate) });
+        self
+    }
+
+    /// Sets the struct field documents may declare their version in. Defaults to `version`.
+    pub fn version_field(mut self, name: impl Into<String>) -> Self {
+        self.version_field = name.into();
+        self
+    }
+
+    /// Sets what comes before the number in version annotations. Defaults to `v`, as in `v3::`.
+    pub fn annotation_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.annotation_prefix = prefix.into();
+        self
+    }
+
+    /// Treats documents which don't declare a version as being at `version`, rather than failing.
+    pub fn unversioned(mut self, version: u32) -> Self {
+        self.unversioned = Some(version);
+        self
+    }
+
+    /// Returns the version documents are migrated to.
+    pub fn current(&self) -> u32 { self.current }
+
+    /// Returns the version `value` declares, or the `unversioned` one if it doesn't declare any.
+    pub fn version_of(&self, value: &IonValue) -> IonResult<u32> {
+        match self.declared_version(value)? {
+            Some(version) => Ok(version),
+            None => self.unversioned.ok_or_else(|| IonError::new(IonErrorType::InvalidValue(format!(
+                "missing version, expected a `{}<n>::` annotation or a `{}` field",
+                self.annotation_prefix, self.version_field,
+            )), Vec::new())),
+        }
+    }
+
+    /// Returns the version `value` declares, if it declares one.
+    fn declared_version(&self, value: &IonValue) -> IonResult<Option<u32>> {
+        if let Some(version) = value.annotations().iter().find_map(|ann| self.annotation_version(ann)) {
+            return version.map(Some);
+        }
+        let invalid = |message: String| IonError::new(IonErrorType::InvalidValue(message), vec![self.version_field.clone()]);
+        match value.as_struct().and_then(|st| st.field(&self.version_field)) {
+            Some(IonValue::Integer(version, _)) => u32::try_from(*version)
+                .map(Some)
+                .map_err(|_| invalid(format!("version {} is out of range", version))),
+            Some(_) => Err(invalid("expected a non-negative integer version".to_string())),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the version named by `annotation`, or `None` if it isn't a version annotation, i.e.
+    /// the prefix followed by an integer. Integers which don't fit a version are an error, as for
+    /// the version field.
+    fn annotation_version(&self, annotation: &str) -> Option<IonResult<u32>> {
+        let number = annotation.strip_prefix(self.annotation_prefix.as_str())?;
+        let digits = number.strip_prefix('-').unwrap_or(number);
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        Some(number.parse::<u32>().map_err(|_| IonError::new(
+            IonErrorType::InvalidValue(format!("version {} is out of range", number)),
+            Vec::new(),
+        )))
+    }
+
+    /// Runs the steps needed to bring `value` up to the current version. Errors from a step are
+    /// reported as `Migration` errors naming it.
+    pub fn migrate(&self, value: IonValue) -> IonResult<IonValue> {
+        let steps = self.steps_from(self.version_of(&value)?)?;
+        self.run(value, &steps)
+    }
+
+    /// Brings every value in `doc` up to the current version. The version is read once, from the
+    /// first value, and applies to the whole document; later values may only declare the same one.
+    pub fn migrate_document(&self, doc: IonDocument) -> IonResult<IonDocument> {
+        let version = match doc.values().first() {
+            Some(first) => self.version_of(first)?,
+            None => return Ok(doc),
+        };
+        for value in &doc.values()[1..] {
+            match self.declared_version(value)? {
+                Some(declared) if declared != version => return Err(IonError::new(IonErrorType::InvalidValue(format!(
+                    "value declares version {}, but the document is version {}", declared, version,
+                )), Vec::new())),
+                _ => {}
+            }
+        }
+        let steps = self.steps_from(version)?;
+        doc.into_values().into_iter().map(|value| self.run(value, &steps)).collect()
+    }
+
+    /// Returns the steps from `version` to the current version, as pairs of the version each
+    /// starts from and the step, checking the whole chain exists before anything is run.
+    fn steps_from(&self, version: u32) -> IonResult<Vec<(u32, &Step)>> {
+        if version > self.current {
+            return Err(IonError::new(IonErrorType::InvalidValue(format!(
+                "version {} is newer than the newest supported version {}", version, self.current,
+            )), Vec::new()));
+        }
+        let mut steps = Vec::new();
+        let mut version = version;
+        while version < self.current {
+            let step = self.steps.get(&version).ok_or_else(|| IonError::new(IonErrorType::Migration {
+                from: version,
+                to: self.current,
+                message: format!("no migration registered from version {}", version),
+            }, Vec::new()))?;
+            if step.to <= version {
+                return Err(IonError::new(IonErrorType::Migration {
+                    from: version,
+                    to: step.to,
+                    message: "migrations must move to a newer version".to_string(),
+                }, Vec::new()));
+            }
+            steps.push((version, step));
+            version = step.to;
+        }
+        Ok(steps)
+    }
+
+    fn run(&self, value: IonValue, steps: &[(u32, &Step)]) -> IonResult<IonValue> {
+        let mut value = value;
+        for (from, step) in steps {
+            value = (step.migrate)(value).map_err(|e| IonError::new(IonErrorType::Migration {
+                from: *from,
+                to: step.to,
+                message: e.ty.to_string(),
+            }, e.scopes))?;
+            self.set_version(&mut value, step.to);
+        }
+        Ok(value)
+    }
+
+    /// Updates the version `value` declares, in the same way it declared the old one.
+    fn set_version(&self, value: &mut IonValue, version: u32) {
+        let annotated = value.annotations().iter()
+            .position(|ann| self.annotation_version(ann).is_some_and(|version| version.is_ok()));
+        if let Some(i) = annotated {
+            value.annotations_mut().set(i, format!("{}{}", self.annotation_prefix, version));
+        }
+        else if let IonValue::Struct(st, _) = value {
+            if st.field(&self.version_field).is_some() {
+                st.insert(Symbol::new(&self.version_field), IonValue::Integer(i64::from(version), Annotations::new()));
+            }
+        }
+    }
+}
+
+impl Debug for Migrations {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let steps: Vec<(u32, u32)> = self.steps.iter().map(|(from, step)| (*from, step.to)).collect();
+        f.debug_struct("Migrations")
+            .field("current", &self.current)
+            .field("steps", &steps)
+            .field("version_field", &self.version_field)
+            .field("annotation_prefix", &self.annotation_prefix)
+            .field("unversioned", &self.unversioned)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use super::*;
+    use crate::test_util::value;
+    use crate::reader::{IonReader, ReadOptions};
+
+    fn rename(from: &'static str, to: &'static str) -> impl Fn(IonValue) -> IonResult<IonValue> {
+        move |mut value| {
+            if let IonValue::Struct(st, _) = &mut value {
+                if let Some(field) = st.field(from).cloned() {
+                    st.insert(to, field);
+                    st.remove(from);
+                }
+            }
+            Ok(value)
+        }
+    }
+
+    fn migrations() -> Migrations {
+        Migrations::new(3)
+            .step(1, 2, rename("speed", "velocity"))
+            .step(2, 3, rename("sprite", "texture"))
+    }
+
+    fn read(text: &str, migrations: Migrations) -> IonResult<Vec<IonValue>> {
+        let options = ReadOptions { migrations: Some(migrations), ..ReadOptions::default() };
+        Ok(IonReader::read_string_with(text, &options)?.into_values())
+    }
+
+    #[rstest(text, expected,
+        case("v1::{ speed: 2, sprite: a }", "v3::{ velocity: 2, texture: a }"),
+        case("{ version: 2, speed: 2, sprite: a }", "{ version: 3, speed: 2, texture: a }"),
+        case("v3::{ speed: 2 }", "v3::{ speed: 2 }"),
+        // annotations with the prefix which don't name a version are left alone
+        case("visible::v2::{ sprite: a }", "visible::v3::{ texture: a }"),
+        // the version annotation keeps its place among the others
+        case("v1::shape::{ speed: 2 }", "v3::shape::{ velocity: 2 }"),
+        case("a::v2::b::{}", "a::v3::b::{}"),
+    )]
+    fn migrates_values(text: &str, expected: &str) {
+        assert_eq!(migrations().migrate(value(text)).unwrap(), value(expected));
+    }
+
+    #[test]
+    fn applies_the_document_version_to_every_value() {
+        let values = read("v1::{ speed: 1 } { speed: 2 } { sprite: a }", migrations()).unwrap();
+        assert_eq!(values, vec![value("v3::{ velocity: 1 }"), value("{ velocity: 2 }"), value("{ texture: a }")]);
+
+        let values = read("v3::{ speed: 1 } { speed: 2 }", migrations().unversioned(1)).unwrap();
+        assert_eq!(values, vec![value("v3::{ speed: 1 }"), value("{ speed: 2 }")]);
+
+        let values = read("{ speed: 1 } { speed: 2 }", migrations().unversioned(2)).unwrap();
+        assert_eq!(values, vec![value("{ speed: 1 }"), value("{ speed: 2 }")]);
+        assert_eq!(read("", migrations()).unwrap(), vec![]);
+    }
+
+    #[rstest(text, migrations, message,
+        case("{ speed: 1 }", migrations(), "missing version"),
+        case("v4::{}", migrations(), "newer than the newest supported version 3"),
+        case("{ version: -1 }", migrations(), "version -1 is out of range"),
+        case("{ version: 4294967297 }", migrations(), "version 4294967297 is out of range"),
+        case("v4294967297::{}", migrations(), "version 4294967297 is out of range"),
+        case("'v-1'::{}", migrations(), "version -1 is out of range"),
+        case("{ version: \"2\" }", migrations(), "expected a non-negative integer version"),
+        case("v1::{} v2::{}", migrations(), "value declares version 2, but the document is version 1"),
+        case("v0::{}", migrations(), "no migration registered from version 0"),
+        case("v1::{}", Migrations::new(3).step(1, 1, Ok), "migrations must move to a newer version"),
+    )]
+    fn reports_errors(text: &str, migrations: Migrations, message: &str) {
+        match read(text, migrations) {
+            Err(e) => assert!(e.to_string().contains(message), "{}", e),
+            Ok(values) => panic!("expected an error, got {:?}", values),
+        }
+    }
+
+    #[test]
+    fn names_the_failing_step() {
+        let migrations = migrations().step(2, 3, |_| Err(IonError::new(IonErrorType::MissingField("sprite".to_string()), vec!["frames".to_string()])));
+        match migrations.migrate(value("v1::{}")) {
+            Err(IonError { ty: IonErrorType::Migration { from: 2, to: 3, message }, scopes }) => {
+                assert_eq!(message, "missing field `sprite`");
+                assert_eq!(scopes, vec!["frames"]);
+            }
+            other => panic!("expected a migration error, got {:?}", other),
+        }
+    }
+}
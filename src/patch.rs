@@ -0,0 +1,379 @@
+//! Patches built from `diff` output, which can be applied, inverted and stored as Ion.
+//!
+//! Stored patches are a list of operations, one per `Change`:
+//!
+//! ```ion
+//! [
+//!   { op: change, path: ["frames", 1, "duration"], old: 200, new: 250 },
+//!   { op: insert, path: ["frames", 3], value: { duration: 400 } },
+//!   { op: remove, path: ["speed"], value: 1.5e0 },
+//! ]
+//! ```
+//!
+//! The other operations are `add`, `retype` (`old` and `new`), `annotate` (`old` and `new`
+//! lists of symbols) and `delete` (`value`).
+
+use std::collections::HashMap;
+use crate::diff::{diff, same, Change, ChangeKind};
+use crate::error::{IonError, IonErrorType, IonResult};
+use crate::path::{IonPath, PathSegment};
+use crate::types::{Annotations, IonList, IonStruct, IonValue};
+
+#[derive(Debug, Clone, Default, PartialEq)]
+/// A set of changes which turns one document into another.
+pub struct IonPatch {
+    changes: Vec<Change>,
+}
+
+/// Changes relative to the value they're being applied to.
+type Relative<'c> = Vec<(&'c [PathSegment], &'c ChangeKind)>;
+
+impl IonPatch {
+    /// Create a patch from the given changes, using the path conventions of `diff`.
+    pub fn new(changes: Vec<Change>) -> Self { IonPatch { changes } }
+
+    /// Create a patch which turns `old` into `new`.
+    pub fn between(old: &IonValue, new: &IonValue) -> Self { IonPatch::new(diff(old, new)) }
+
+    /// Returns the changes making up this patch.
+    pub fn changes(&self) -> &[Change] { &self.changes }
+
+    /// Returns true if this patch doesn't change anything.
+    pub fn is_empty(&self) -> bool { self.changes.is_empty() }
+
+    /// Returns a patched copy of `target`.
+    ///
+    /// Fails with `PatchConflict` if `target` doesn't match the old values recorded in the patch,
+    /// e.g. a removed field is missing or a changed value has since been edited.
+    pub fn apply(&self, target: &IonValue) -> IonResult<IonValue> {
+        let mut patched = target.clone();
+        let changes = self.changes.iter().map(|c| (c.path.segments(), &c.kind)).collect();
+        apply_value(&IonPath::root(), &mut patched, changes)?;
+        Ok(patched)
+    }
+
+    /// Patches `target` in place. `target` is left unchanged if the patch doesn't apply.
+    pub fn apply_in_place(&self, target: &mut IonValue) -> IonResult<()> {
+        *target = self.apply(target)?;
+        Ok(())
+    }
+
+    /// Returns the patch which undoes this one: applying both in turn gives back the original.
+    pub fn invert(&self) -> IonPatch {
+        // inverted list indices refer to the new document, so positions of elements which
+        // survive the patch have to be shifted past the insertions and deletions around them
+        let mut edits: HashMap<IonPath, (Vec<usize>, Vec<usize>)> = HashMap::new();
+        for change in self.changes.iter() {
+            if let (Some(PathSegment::Index(i)), Some(list)) = (change.path.last(), change.path.parent()) {
+                match change.kind {
+                    ChangeKind::Deleted(_) => edits.entry(list).or_default().0.push(*i),
+                    ChangeKind::Inserted(_) => edits.entry(list).or_default().1.push(*i),
+                    _ => {}
+                }
+            }
+        }
+        for (deleted, inserted) in edits.values_mut() {
+            deleted.sort_unstable();
+            inserted.sort_unstable();
+        }
+
+        let changes = self.changes.iter().map(|change| {
+            let structural = matches!(change.kind, ChangeKind::Inserted(_) | ChangeKind::Deleted(_));
+            let segments = change.path.segments();
+            let mut prefix = IonPath::root();
+            let mut mapped = Vec::with_capacity(segments.len());
+            for (n, segment) in segments.iter().enumerate() {
+                let last = n + 1 == segments.len();
+                mapped.push(match (segment, edits.get(&prefix)) {
+                    (PathSegment::Index(i), Some((deleted, inserted))) if !(last && structural) => {
+                        PathSegment::Index(shift_index(*i, deleted, inserted))
+                    }
+                    _ => segment.clone(),
+                });
+                prefix = prefix.child(segment.clone());
+            }
+            let kind = match &change.kind {
+                ChangeKind::Added(v) => ChangeKind::Removed(v.clone()),
+                ChangeKind::Removed(v) => ChangeKind::Added(v.clone()),
+                ChangeKind::Inserted(v) => ChangeKind::Deleted(v.clone()),
+                ChangeKind::Deleted(v) => ChangeKind::Inserted(v.clone()),
+                ChangeKind::Changed { old, new } => ChangeKind::Changed { old: new.clone(), new: old.clone() },
+                ChangeKind::TypeChanged { old, new } => ChangeKind::TypeChanged { old: new.clone(), new: old.clone() },
+                ChangeKind::AnnotationsChanged { old, new } =>
+                    ChangeKind::AnnotationsChanged { old: new.clone(), new: old.clone() },
+            };
+            Change { path: IonPath::new(mapped), kind }
+        }).collect();
+        IonPatch { changes }
+    }
+
+    /// Converts this patch to its Ion representation (see the module documentation).
+    pub fn to_ion(&self) -> IonValue {
+        let items = self.changes.iter().map(|change| {
+            let mut st = IonStruct::new_empty();
+            let (op, fields) = match &change.kind {
+                ChangeKind::Added(v) => ("add", vec![("value", v.clone())]),
+                ChangeKind::Removed(v) => ("remove", vec![("value", v.clone())]),
+                ChangeKind::Inserted(v) => ("insert", vec![("value", v.clone())]),
+                ChangeKind::Deleted(v) => ("delete", vec![("value", v.clone())]),
+                ChangeKind::Changed { old, new } => ("change", vec![("old", old.clone()), ("new", new.clone())]),
+                ChangeKind::TypeChanged { old, new } => ("retype", vec![("old", old.clone()), ("new", new.clone())]),
+                ChangeKind::AnnotationsChanged { old, new } =>
+                    ("annotate", vec![("old", symbol_list(old)), ("new", symbol_list(new))]),
+            };
+            st.insert("op", IonValue::Symbol(op.into(), Annotations::new()));
+            let path = change.path.segments().iter().map(|segment| match segment {
+                PathSegment::Field(name) => IonValue::String(name.clone(), Annotations::new()),
+                PathSegment::Index(i) => IonValue::Integer(*i as i64, Annotations::new()),
+            }).collect();
+            st.insert("path", IonValue::List(IonList { items: path }, Annotations::new()));
+            for (name, value) in fields {
+                st.insert(name, value);
+            }
+            IonValue::Struct(st, Annotations::new())
+        }).collect();
+        IonValue::List(IonList { items }, Annotations::new())
+    }
+
+    /// Reads a patch from its Ion representation (see the module documentation).
+    pub fn from_ion(value: &IonValue) -> IonResult<IonPatch> {
+        let list = value.as_list().ok_or_else(|| invalid("a patch must be a list", Vec::new()))?;
+        let mut changes = Vec::with_capacity(list.len());
+        for (i, item) in list.iter().enumerate() {
+            let scopes = vec![format!("[{}]", i)];
+            let st = item.as_struct().ok_or_else(|| invalid("a patch operation must be a struct", scopes.clone()))?;
+            let field = |name: &str| st.field(name).cloned()
+                .ok_or_else(|| IonError::new(IonErrorType::MissingField(name.to_string()), scopes.clone()));
+            let mut path = Vec::new();
+            for segment in field("path")?.as_list().ok_or_else(|| invalid("`path` must be a list", scopes.clone()))?.iter() {
+                path.push(match segment {
+                    IonValue::String(name, _) => PathSegment::Field(name.clone()),
+                    IonValue::Symbol(name, _) => PathSegment::Field(name.to_string()),
+                    IonValue::Integer(i, _) if *i >= 0 => PathSegment::Index(*i as usize),
+                    _ => return Err(invalid("path segments must be field names or indices", scopes)),
+                });
+            }
+            let kind = match field("op")?.as_text() {
+                Some("add") => ChangeKind::Added(field("value")?),
+                Some("remove") => ChangeKind::Removed(field("value")?),
+                Some("insert") => ChangeKind::Inserted(field("value")?),
+                Some("delete") => ChangeKind::Deleted(field("value")?),
+                Some("change") => ChangeKind::Changed { old: field("old")?, new: field("new")? },
+                Some("retype") => ChangeKind::TypeChanged { old: field("old")?, new: field("new")? },
+                Some("annotate") => ChangeKind::AnnotationsChanged {
+                    old: annotations_of(&field("old")?, &scopes)?,
+                    new: annotations_of(&field("new")?, &scopes)?,
+                },
+                _ => return Err(invalid("unknown `op`", scopes)),
+            };
+            changes.push(Change { path: IonPath::new(path), kind });
+        }
+        Ok(IonPatch { changes })
+    }
+}
+
+/// Returns the position of the element at old index `i` once `deleted` old indices are removed
+/// and `inserted` new indices are filled. Both must be sorted.
+fn shift_index(i: usize, deleted: &[usize], inserted: &[usize]) -> usize {
+    let mut pos = i - deleted.iter().take_while(|d| **d < i).count();
+    for ins in inserted {
+        if *ins <= pos { pos += 1 } else { break }
+    }
+    pos
+}
+
+fn symbol_list(annotations: &Annotations) -> IonValue {
+    let items = annotations.iter().map(|ann| IonValue::Symbol(ann.clone(), Annotations::new())).collect();
+    IonValue::List(IonList { items }, Annotations::new())
+}
+
+fn annotations_of(value: &IonValue, scopes: &[String]) -> IonResult<Annotations> {
+    let list = value.as_list().ok_or_else(|| invalid("annotations must be a list", scopes.to_vec()))?;
+    list.iter()
+        .map(|ann| ann.as_text().map(str::to_string)
+            .ok_or_else(|| invalid("annotations must be symbols", scopes.to_vec())))
+        .collect()
+}
+
+fn invalid(message: &str, scopes: Vec<String>) -> IonError {
+    IonError::new(IonErrorType::InvalidValue(message.to_string()), scopes)
+}
+
+fn conflict(path: &IonPath, message: String) -> IonError {
+    IonError::new(IonErrorType::PatchConflict(message), path.to_scopes())
+}
+
+fn expect_same(path: &IonPath, found: &IonValue, expected: &IonValue) -> IonResult<()> {
+    if same(found, expected) { Ok(()) }
+    else { Err(conflict(path, format!("expected {}, found {}", expected, found))) }
+}
+
+fn apply_value(path: &IonPath, value: &mut IonValue, changes: Relative) -> IonResult<()> {
+    let mut nested: HashMap<&PathSegment, Relative> = HashMap::new();
+    let mut structural = Vec::new();
+    for (segments, kind) in changes {
+        let edits_parent = matches!(kind, ChangeKind::Added(_) | ChangeKind::Removed(_)
+                                        | ChangeKind::Inserted(_) | ChangeKind::Deleted(_));
+        match segments {
+            [] if edits_parent => return Err(conflict(path, "the root value can't be added or removed".to_string())),
+            [] => apply_here(path, value, kind)?,
+            [segment] if edits_parent => structural.push((segment, kind)),
+            [first, rest @ ..] => nested.entry(first).or_default().push((rest, kind)),
+        }
+    }
+
+    // nested changes use old indices, so apply them before the list is rearranged
+    for (segment, changes) in nested {
+        let child_path = path.child(segment.clone());
+        let child = match (segment, &mut *value) {
+            (PathSegment::Field(name), IonValue::Struct(st, _)) => st.field_mut(name),
+            (PathSegment::Index(i), IonValue::List(list, _)) => list.items.get_mut(*i),
+            _ => None,
+        };
+        match child {
+            Some(child) => apply_value(&child_path, child, changes)?,
+            None => return Err(conflict(&child_path, "no such value".to_string())),
+        }
+    }
+
+    match value {
+        IonValue::Struct(st, _) => {
+            for (segment, kind) in structural {
+                let child_path = path.child(segment.clone());
+                let name = match segment {
+                    PathSegment::Field(name) => name,
+                    PathSegment::Index(_) => return Err(conflict(&child_path, "expected a list".to_string())),
+                };
+                match kind {
+                    ChangeKind::Added(new) => {
+                        if let Some(existing) = st.field(name) {
+                            return Err(conflict(&child_path, format!("field already exists with value {}", existing)));
+                        }
+                        st.insert(name.clone(), new.clone());
+                    }
+                    ChangeKind::Removed(old) => match st.field(name) {
+                        Some(existing) => {
+                            expect_same(&child_path, existing, old)?;
+                            st.remove(name);
+                        }
+                        None => return Err(conflict(&child_path, "no such field".to_string())),
+                    },
+                    _ => return Err(conflict(&child_path, "expected a list".to_string())),
+                }
+            }
+        }
+        IonValue::List(list, _) => {
+            let mut deleted = Vec::new();
+            let mut inserted = Vec::new();
+            for (segment, kind) in structural {
+                let child_path = path.child(segment.clone());
+                let i = match segment {
+                    PathSegment::Index(i) => *i,
+                    PathSegment::Field(_) => return Err(conflict(&child_path, "expected a struct".to_string())),
+                };
+                match kind {
+                    ChangeKind::Deleted(old) => match list.at(i) {
+                        Some(existing) => {
+                            expect_same(&child_path, existing, old)?;
+                            deleted.push(i);
+                        }
+                        None => return Err(conflict(&child_path, "no such element".to_string())),
+                    },
+                    ChangeKind::Inserted(new) => inserted.push((i, new)),
+                    _ => return Err(conflict(&child_path, "expected a struct".to_string())),
+                }
+            }
+            deleted.sort_unstable();
+            let mut items: Vec<IonValue> = std::mem::take(&mut list.items).into_iter().enumerate()
+                .filter(|(i, _)| deleted.binary_search(i).is_err())
+                .map(|(_, item)| item)
+                .collect();
+            inserted.sort_by_key(|(i, _)| *i);
+            for (i, new) in inserted {
+                if i > items.len() {
+                    return Err(conflict(&path.index(i), format!("can't insert into a list of length {}", items.len())));
+                }
+                items.insert(i, new.clone());
+            }
+            list.items = items;
+        }
+        _ => {
+            if let Some((segment, _)) = structural.first() {
+                return Err(conflict(&path.child((*segment).clone()), "expected a struct or list".to_string()));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn apply_here(path: &IonPath, value: &mut IonValue, kind: &ChangeKind) -> IonResult<()> {
+    match kind {
+        ChangeKind::Changed { old, new } | ChangeKind::TypeChanged { old, new } => {
+            expect_same(path, value, old)?;
+            *value = new.clone();
+        }
+        ChangeKind::AnnotationsChanged { old, new } => {
+            if value.annotations() != old {
+                return Err(conflict(path, format!("expected annotations [{}], found [{}]",
+                                                  old.join(", "), value.annotations().join(", "))));
+            }
+            *value.annotations_mut() = new.clone();
+        }
+        _ => unreachable!("structural changes are applied to the parent"),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use super::*;
+    use crate::test_util::value;
+
+    #[rstest(old, new,
+        case("{ a: 1, b: [1, 2, 3] }", "{ a: 2, b: [1, 3], c: x }"),
+        case("[1, 2, 3, 4, 5]", "[0, 2, 9, 4, 6, 7]"),
+        case("[{ d: 1 }, { d: 2 }, { d: 3 }]", "[{ d: 0 }, { d: 2, e: 1 }]"),
+        case("{ frames: [a, b, c, d] }", "{ frames: [x, b, y, d, z] }"),
+        case("sprite::{ a: [1, [2, 3]] }", "anim::{ a: [[2, 4], 1] }"),
+        case("{ a: 1 }", "{ a: \"1\" }"),
+    )]
+    fn invert_round_trips(old: &str, new: &str) {
+        let (old, new) = (value(old), value(new));
+        let patch = IonPatch::between(&old, &new);
+        let patched = patch.apply(&old).unwrap();
+        assert!(same(&patched, &new), "{} != {}", patched, new);
+        let restored = patch.invert().apply(&patched).unwrap();
+        assert!(same(&restored, &old), "{} != {}", restored, old);
+    }
+
+    #[rstest(target,
+        case("{ frames: [1, 2, 3], speed: 2 }"),
+        case("{ frames: [1, 2, 3] }"),
+        case("{ frames: [1, 5, 3], speed: 1.5e0 }"),
+        case("{ frames: 1, speed: 1.5e0 }"),
+    )]
+    fn reports_conflicts(target: &str) {
+        let patch = IonPatch::between(&value("{ frames: [1, 2, 3], speed: 1.5e0 }"), &value("{ frames: [1, 4, 3] }"));
+        match patch.apply(&value(target)) {
+            Err(IonError { ty: IonErrorType::PatchConflict(_), .. }) => {}
+            other => panic!("expected a patch conflict, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn apply_in_place_leaves_target_on_conflict() {
+        let patch = IonPatch::between(&value("{ a: 1, b: 2 }"), &value("{ a: 3 }"));
+        let mut target = value("{ a: 1 }");
+        assert!(patch.apply_in_place(&mut target).is_err());
+        assert_eq!(target, value("{ a: 1 }"));
+    }
+
+    #[test]
+    fn ion_representation_round_trips() {
+        let patch = IonPatch::between(&value("{ a: 1, l: [1, 2] }"), &value("t::{ b: 2, l: [0, 1] }"));
+        let parsed = IonPatch::from_ion(&patch.to_ion()).unwrap();
+        assert_eq!(parsed, patch);
+        assert!(IonPatch::from_ion(&value("[{ op: frobnicate, path: [] }]")).is_err());
+    }
+}
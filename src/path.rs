@@ -0,0 +1,158 @@
+use std::fmt::{Display, Formatter};
+use crate::types::IonValue;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// A single step in an `IonPath`.
+pub enum PathSegment {
+    /// A named field of a struct.
+    Field(String),
+    /// An index into a list.
+    Index(usize),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+/// A location inside an `IonValue` tree, displayed like `animations[2].frames`.
+/// The empty path refers to the root value and is displayed as `(root)`.
+pub struct IonPath {
+    segments: Vec<PathSegment>,
+}
+
+impl IonPath {
+    /// Create a path referring to the root value.
+    pub fn root() -> Self { IonPath { segments: Vec::new() } }
+    /// Create a path from the given segments.
+    pub fn new(segments: Vec<PathSegment>) -> Self { IonPath { segments } }
+
+    /// Returns true if this path refers to the root value.
+    pub fn is_root(&self) -> bool { self.segments.is_empty() }
+    /// Returns the segments making up this path.
+    pub fn segments(&self) -> &[PathSegment] { &self.segments }
+    /// Returns the last segment of this path, or `None` for the root.
+    pub fn last(&self) -> Option<&PathSegment> { self.segments.last() }
+
+    /// Returns the path to the parent value, or `None` for the root.
+    pub fn parent(&self) -> Option<IonPath> {
+        if self.segments.is_empty() { return None; }
+        Some(IonPath { segments: self.segments[..self.segments.len() - 1].to_vec() })
+    }
+
+    /// Returns a copy of this path extended with the given field name.
+    pub fn field(&self, name: impl AsRef<str>) -> IonPath {
+        self.child(PathSegment::Field(name.as_ref().to_string()))
+    }
+
+    /// Returns a copy of this path extended with the given list index.
+    pub fn index(&self, index: usize) -> IonPath {
+        self.child(PathSegment::Index(index))
+    }
+
+    /// Returns a copy of this path extended with the given segment.
+    pub fn child(&self, segment: PathSegment) -> IonPath {
+        let mut segments = self.segments.clone();
+        segments.push(segment);
+        IonPath { segments }
+    }
+
+    /// Returns the value this path refers to inside `root`, or `None` if it doesn't exist.
+    pub fn resolve<'v>(&self, root: &'v IonValue) -> Option<&'v IonValue> {
+        let mut value = root;
+        for segment in self.segments.iter() {
+            value = match segment {
+                PathSegment::Field(name) => value.as_struct()?.field(name)?,
+                PathSegment::Index(i) => value.as_list()?.at(*i)?,
+            };
+        }
+        Some(value)
+    }
+
+    /// Returns a mutable reference to the value this path refers to inside `root`,
+    /// or `None` if it doesn't exist.
+    pub fn resolve_mut<'v>(&self, root: &'v mut IonValue) -> Option<&'v mut IonValue> {
+        let mut value = root;
+        for segment in self.segments.iter() {
+            value = match (segment, value) {
+                (PathSegment::Field(name), IonValue::Struct(st, _)) => st.field_mut(name)?,
+                (PathSegment::Index(i), IonValue::List(list, _)) => list.items.get_mut(*i)?,
+                _ => return None,
+            };
+        }
+        Some(value)
+    }
+
+    /// Converts this path into `IonError` scopes, so errors about it render the same way.
+    pub fn to_scopes(&self) -> Vec<String> {
+        self.segments.iter()
+            .map(|segment| match segment {
+                PathSegment::Field(name) => name.clone(),
+                PathSegment::Index(i) => format!("[{}]", i),
+            })
+            .collect()
+    }
+}
+
+impl Display for IonPath {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.segments.is_empty() {
+            return write!(f, "(root)");
+        }
+        for (i, segment) in self.segments.iter().enumerate() {
+            match segment {
+                PathSegment::Field(name) if is_identifier(name) => {
+                    if i > 0 { write!(f, ".")?; }
+                    write!(f, "{}", name)?;
+                }
+                PathSegment::Field(name) => write!(f, "[{:?}]", name)?,
+                PathSegment::Index(idx) => write!(f, "[{}]", idx)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns true if `s` can be written as an unquoted Ion identifier.
+pub(crate) fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' || c == '$' => {}
+        _ => return false,
+    }
+    // unquoted `$10` is read back as symbol ID 10, not as the text `$10`
+    if s.len() > 1 && s.starts_with('$') && s[1..].bytes().all(|b| b.is_ascii_digit()) {
+        return false;
+    }
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use super::*;
+
+    #[rstest(s, expected,
+        case("frames", true),
+        case("_private", true),
+        case("$ion", true),
+        case("$1a", true),
+        case("$", true),
+        case("$10", false),
+        case("$0", false),
+        case("2d", false),
+        case("odd key", false),
+        case("", false),
+    )]
+    fn identifiers(s: &str, expected: bool) {
+        assert_eq!(is_identifier(s), expected);
+    }
+
+    #[test]
+    fn displays_paths() {
+        let path = IonPath::new(vec![
+            PathSegment::Field("animations".to_string()),
+            PathSegment::Index(2),
+            PathSegment::Field("$10".to_string()),
+            PathSegment::Field("odd key".to_string()),
+        ]);
+        assert_eq!(path.to_string(), r#"animations[2]["$10"]["odd key"]"#);
+        assert_eq!(IonPath::root().to_string(), "(root)");
+    }
+}
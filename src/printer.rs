@@ -0,0 +1,391 @@
+use std::fmt::{Display, Formatter};
+use crate::path::is_identifier;
+use crate::document::IonDocument;
+use crate::types::{IonValue, Symbol};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// When symbols (including annotations) are written in quotes.
+pub enum SymbolQuoting {
+    /// Only quote symbols that can't be written as identifiers, like `'hello world'` or `'null'`.
+    Minimal,
+    /// Always quote symbols, e.g. `'walk'`.
+    Always,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// How struct field names are written.
+pub enum FieldNames {
+    /// As symbols, following the `SymbolQuoting` setting: `{name: "hero"}`.
+    Symbols,
+    /// As strings: `{"name": "hero"}`.
+    Strings,
+}
+
+#[derive(Debug, Clone)]
+/// Options for `PrettyPrinter`.
+pub struct PrettyConfig {
+    /// Number of spaces per indentation level.
+    pub indent: usize,
+    /// Containers which fit within this many columns are kept on one line; longer lists of
+    /// scalars are wrapped to this width, and other containers get one item per line.
+    pub max_width: usize,
+    /// Write everything on a single line, ignoring `indent` and `max_width`.
+    pub compact: bool,
+    /// Write struct fields sorted by name instead of in `IonStruct` iteration order.
+    pub sort_keys: bool,
+    pub symbol_quoting: SymbolQuoting,
+    pub field_names: FieldNames,
+}
+
+impl Default for PrettyConfig {
+    fn default() -> Self {
+        PrettyConfig {
+            indent: 2,
+            max_width: 80,
+            compact: false,
+            sort_keys: false,
+            symbol_quoting: SymbolQuoting::Minimal,
+            field_names: FieldNames::Symbols,
+        }
+    }
+}
+
+impl PrettyConfig {
+    /// The default config, but writing everything on one line.
+    pub fn compact() -> Self {
+        PrettyConfig { compact: true, ..Default::default() }
+    }
+}
+
+/// Writes `IonValue`s as Ion text.
+pub struct PrettyPrinter {
+    config: PrettyConfig,
+}
+
+impl PrettyPrinter {
+    /// Create a new `PrettyPrinter` with the given config.
+    pub fn new(config: PrettyConfig) -> Self { PrettyPrinter { config } }
+
+    /// Returns a reference to this printer's config.
+    pub fn config(&self) -> &PrettyConfig { &self.config }
+
+    /// Writes a single value as Ion text.
+    pub fn print(&self, value: &IonValue) -> String {
+        let mut out = String::new();
+        self.write_value(&mut out, value, 0, 0);
+        out
+    }
+
+    /// Writes the given values as a stream of top-level values, one per line.
+    pub fn print_all(&self, values: &[IonValue]) -> String {
+        let mut out = String::new();
+        for value in values {
+            self.write_value(&mut out, value, 0, 0);
+            out.push('\n');
+        }
+        out
+    }
+
+    fn newline(&self, out: &mut String, depth: usize) {
+        out.push('\n');
+        out.push_str(&" ".repeat(depth * self.config.indent));
+    }
+
+    /// Returns the length of the current (last) line of `out`.
+    fn column(out: &str, start: usize) -> usize {
+        match out[start..].rfind('\n') {
+            Some(i) => out.len() - start - i - 1,
+            None => out.len() - start,
+        }
+    }
+
+    fn write_value(&self, out: &mut String, value: &IonValue, depth: usize, column: usize) {
+        let start = out.len();
+        self.write_annotations(out, value);
+        let column = column + (out.len() - start);
+        match value {
+            IonValue::List(list, _) if list.len() > 0 => {
+                if self.try_flat(out, value, column) { return; }
+                let scalars = list.iter().all(|v| !matches!(v, IonValue::List(..) | IonValue::Struct(..)));
+                out.push('[');
+                if scalars {
+                    // fill lines with as many items as fit
+                    self.newline(out, depth + 1);
+                    let mut line_start = out.len() - (depth + 1) * self.config.indent;
+                    for (i, item) in list.iter().enumerate() {
+                        let mut text = String::new();
+                        self.write_flat(&mut text, item);
+                        if i > 0 {
+                            out.push(',');
+                            let width = out.len() - line_start + 1 + text.len();
+                            if width > self.config.max_width {
+                                self.newline(out, depth + 1);
+                                line_start = out.len() - (depth + 1) * self.config.indent;
+                            }
+                            else {
+                                out.push(' ');
+                            }
+                        }
+                        out.push_str(&text);
+                    }
+                }
+                else {
+                    for (i, item) in list.iter().enumerate() {
+                        if i > 0 { out.push(','); }
+                        self.newline(out, depth + 1);
+                        self.write_value(out, item, depth + 1, (depth + 1) * self.config.indent);
+                    }
+                }
+                self.newline(out, depth);
+                out.push(']');
+            }
+            IonValue::Struct(st, _) if st.iter_fields().next().is_some() => {
+                if self.try_flat(out, value, column) { return; }
+                out.push('{');
+                for (i, (name, field)) in self.fields(value).into_iter().enumerate() {
+                    if i > 0 { out.push(','); }
+                    self.newline(out, depth + 1);
+                    let field_start = out.len() - (depth + 1) * self.config.indent;
+                    self.write_field_name(out, name);
+                    out.push_str(": ");
+                    let column = PrettyPrinter::column(out, field_start);
+                    self.write_value(out, field, depth + 1, column);
+                }
+                self.newline(out, depth);
+                out.push('}');
+            }
+            _ => self.write_bare_flat(out, value),
+        }
+    }
+
+    /// Writes `value` on one line if the config is compact or it fits in the remaining width.
+    fn try_flat(&self, out: &mut String, value: &IonValue, column: usize) -> bool {
+        if self.config.compact {
+            self.write_bare_flat(out, value);
+            return true;
+        }
+        let budget = self.config.max_width.saturating_sub(column);
+        let mut text = String::new();
+        if self.write_flat_limited(&mut text, value, budget, false) {
+            out.push_str(&text);
+            true
+        }
+        else {
+            false
+        }
+    }
+
+    fn write_flat(&self, out: &mut String, value: &IonValue) {
+        self.write_flat_limited(out, value, usize::MAX, true);
+    }
+
+    fn write_bare_flat(&self, out: &mut String, value: &IonValue) {
+        self.write_flat_limited(out, value, usize::MAX, false);
+    }
+
+    /// Writes `value` on one line, giving up (and returning false) once the output from this call
+    /// exceeds `limit` bytes, so deciding whether huge containers fit stays cheap.
+    fn write_flat_limited(&self, out: &mut String, value: &IonValue, limit: usize, annotations: bool) -> bool {
+        let start = out.len();
+        if annotations {
+            self.write_annotations(out, value);
+        }
+        match value {
+            IonValue::Null(_) => out.push_str("null"),
+            IonValue::Boolean(b, _) => out.push_str(if *b { "true" } else { "false" }),
+            IonValue::Integer(i, _) => out.push_str(&i.to_string()),
+            IonValue::Float(f, _) => write_float(out, *f),
+            IonValue::Timestamp(ts, _) => out.push_str(&ts.to_rfc3339()),
+            IonValue::Blob(b, _) => {
+                out.push_str("{{");
+                out.push_str(&base64::encode(b));
+                out.push_str("}}");
+            }
+            IonValue::String(s, _) => write_quoted(out, s, '"'),
+            IonValue::Symbol(s, _) => self.write_symbol(out, s),
+            IonValue::List(list, _) => {
+                out.push('[');
+                for (i, item) in list.iter().enumerate() {
+                    if i > 0 { out.push_str(", "); }
+                    let remaining = limit.saturating_sub(out.len() - start);
+                    if !self.write_flat_limited(out, item, remaining, true) { return false; }
+                }
+                out.push(']');
+            }
+            IonValue::Struct(_, _) => {
+                out.push('{');
+                for (i, (name, field)) in self.fields(value).into_iter().enumerate() {
+                    if i > 0 { out.push_str(", "); }
+                    self.write_field_name(out, name);
+                    out.push_str(": ");
+                    let remaining = limit.saturating_sub(out.len() - start);
+                    if !self.write_flat_limited(out, field, remaining, true) { return false; }
+                }
+                out.push('}');
+            }
+        }
+        out.len() - start <= limit
+    }
+
+    fn fields<'v>(&self, value: &'v IonValue) -> Vec<(&'v Symbol, &'v IonValue)> {
+        let mut fields: Vec<_> = match value.as_struct() {
+            Some(st) => st.iter_fields().collect(),
+            None => Vec::new(),
+        };
+        if self.config.sort_keys {
+            fields.sort_by(|a, b| a.0.cmp(b.0));
+        }
+        fields
+    }
+
+    fn write_annotations(&self, out: &mut String, value: &IonValue) {
+        for ann in value.annotations().iter() {
+            self.write_symbol(out, ann);
+            out.push_str("::");
+        }
+    }
+
+    fn write_field_name(&self, out: &mut String, name: &str) {
+        match self.config.field_names {
+            FieldNames::Symbols => self.write_symbol(out, name),
+            FieldNames::Strings => write_quoted(out, name, '"'),
+        }
+    }
+
+    fn write_symbol(&self, out: &mut String, symbol: &str) {
+        let keyword = matches!(symbol, "null" | "true" | "false" | "nan");
+        if self.config.symbol_quoting == SymbolQuoting::Minimal && is_identifier(symbol) && !keyword {
+            out.push_str(symbol);
+        }
+        else {
+            write_quoted(out, symbol, '\'');
+        }
+    }
+}
+
+fn write_float(out: &mut String, f: f64) {
+    if f.is_nan() { out.push_str("nan") }
+    else if f == f64::INFINITY { out.push_str("+inf") }
+    else if f == f64::NEG_INFINITY { out.push_str("-inf") }
+    // the exponent keeps it a float rather than a decimal
+    else { out.push_str(&format!("{:e}", f)) }
+}
+
+fn write_quoted(out: &mut String, s: &str, quote: char) {
+    out.push(quote);
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c == quote => {
+                out.push('\\');
+                out.push(c);
+            }
+            c if (c as u32) < 0x20 || c == '\u{7f}' => out.push_str(&format!("\\x{:02x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push(quote);
+}
+
+impl Display for IonValue {
+    /// Writes the value as compact, single-line Ion text.
+    /// Use the alternate flag (`{:#}`) for multi-line output with the default `PrettyConfig`.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let config = if f.alternate() { PrettyConfig::default() } else { PrettyConfig::compact() };
+        f.write_str(&PrettyPrinter::new(config).print(self))
+    }
+}
+
+impl Display for IonDocument {
+    /// Writes each top-level value on its own line, compactly unless the alternate flag is set.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let config = if f.alternate() { PrettyConfig::default() } else { PrettyConfig::compact() };
+        f.write_str(&PrettyPrinter::new(config).print_all(self.values()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reader::IonReader;
+    use crate::types::Annotations;
+    use crate::test_util::value;
+
+    fn print(config: PrettyConfig, text: &str) -> String {
+        PrettyPrinter::new(config).print(&value(text))
+    }
+
+    #[test]
+    fn keeps_short_containers_on_one_line() {
+        let config = PrettyConfig { sort_keys: true, ..Default::default() };
+        assert_eq!(print(config, "{name: \"hero\", frames: [1, 2]}"), "{frames: [1, 2], name: \"hero\"}");
+    }
+
+    #[test]
+    fn wraps_long_lists_of_scalars() {
+        let config = PrettyConfig { max_width: 20, ..Default::default() };
+        assert_eq!(print(config, "[walk, walk, walk, walk, walk, walk, walk]"),
+            "[\n  walk, walk, walk,\n  walk, walk, walk,\n  walk\n]");
+    }
+
+    #[test]
+    fn puts_nested_containers_on_their_own_lines() {
+        let config = PrettyConfig { max_width: 20, sort_keys: true, ..Default::default() };
+        assert_eq!(print(config, "{name: \"hero\", frames: [{x: 1}, {x: 2}]}"),
+            "{\n  frames: [\n    {x: 1},\n    {x: 2}\n  ],\n  name: \"hero\"\n}");
+    }
+
+    #[test]
+    fn writes_compact_output_on_one_line() {
+        let config = PrettyConfig { max_width: 10, sort_keys: true, ..PrettyConfig::compact() };
+        assert_eq!(print(config, "sprite::{name: \"hero\", frames: [{x: 1}, {x: 2}]}"),
+            "sprite::{frames: [{x: 1}, {x: 2}], name: \"hero\"}");
+    }
+
+    #[test]
+    fn sorts_keys() {
+        let config = PrettyConfig { sort_keys: true, ..PrettyConfig::compact() };
+        assert_eq!(print(config, "{name: \"hero\", frames: {walk: 2, idle: 1}}"),
+            "{frames: {idle: 1, walk: 2}, name: \"hero\"}");
+    }
+
+    #[test]
+    fn writes_field_names_as_strings() {
+        let config = PrettyConfig { field_names: FieldNames::Strings, sort_keys: true, ..PrettyConfig::compact() };
+        assert_eq!(print(config, "{name: hero, 'odd key': 1}"), "{\"name\": hero, \"odd key\": 1}");
+    }
+
+    #[test]
+    fn always_quotes_symbols() {
+        let config = PrettyConfig { symbol_quoting: SymbolQuoting::Always, ..PrettyConfig::compact() };
+        assert_eq!(print(config, "sprite::{name: hero}"), "'sprite'::{'name': 'hero'}");
+    }
+
+    #[test]
+    fn quotes_symbols_only_when_needed() {
+        assert_eq!(print(PrettyConfig::compact(), "[hero, 'odd key', 'null', 'true']"), "[hero, 'odd key', 'null', 'true']");
+    }
+
+    #[test]
+    fn symbol_ids_are_quoted_when_printed() {
+        let value = IonValue::Symbol(Symbol::new("$10"), Annotations::new());
+        assert_eq!(PrettyPrinter::new(PrettyConfig::default()).print(&value), "'$10'");
+    }
+
+    #[test]
+    fn display_round_trips() {
+        let original = value(r#"
+            sprite::{
+                name: "hero \"one\"\n",
+                frames: [1, -2.5e0, +inf, walk, 'odd key', '$10', null.string],
+                data: {{aGVybw==}},
+                nested: { enabled: true, at: 2020-01-02T03:04:05Z },
+            }
+        "#);
+        assert_eq!(IonReader::read_string(&original.to_string()).unwrap().into_single().unwrap(), original);
+        assert_eq!(IonReader::read_string(&format!("{:#}", original)).unwrap().into_single().unwrap(), original);
+    }
+}
@@ -0,0 +1,261 @@
+//! Struct inheritance for data-driven assets.
+//!
+//! A set of named definitions can build on each other with an `extends` field naming one or more
+//! parents. Parents are deep-merged under the child (in order, if there are several), so the
+//! child only has to list what's different:
+//!
+//! ```ion
+//! {
+//!   base_enemy: { hp: 10, speed: 1.0, loot: { gold: 5 } },
+//!   goblin: { extends: "base_enemy", hp: 6, loot: { dagger: 1 } },
+//!   goblin_chief: { extends: goblin, hp: 20, speed: delete::null },
+//! }
+//! ```
+//!
+//! Structs nested inside a definition can extend definitions too. Merging follows `merge`,
+//! so `delete::` removes an inherited field, and is dropped where there's nothing to remove.
+
+use std::collections::HashMap;
+use crate::document::IonDocument;
+use crate::error::{IonError, IonErrorType, IonResult};
+use crate::merge::{merge, strip_deletes, MergeOptions};
+use crate::path::IonPath;
+use crate::traits::IonDeserialize;
+use crate::types::{Annotations, IonList, IonStruct, IonType, IonValue};
+use crate::walker::IonWalker;
+
+#[derive(Debug, Clone)]
+/// Options for `Prototypes`.
+pub struct PrototypeOptions {
+    /// Name of the field holding the parent name (or a list of names).
+    pub field: String,
+    /// How a child is merged over its parents.
+    pub merge: MergeOptions,
+}
+
+impl Default for PrototypeOptions {
+    fn default() -> Self {
+        PrototypeOptions { field: "extends".to_string(), merge: MergeOptions::default() }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// A set of named definitions with inheritance resolved.
+pub struct Prototypes {
+    resolved: HashMap<String, IonValue>,
+    options: PrototypeOptions,
+}
+
+impl Prototypes {
+    /// Resolves every field of `defs` as a named definition.
+    pub fn new(defs: &IonStruct) -> IonResult<Self> {
+        Prototypes::with_options(defs, PrototypeOptions::default())
+    }
+
+    /// Resolves every field of `defs` as a named definition, using the given options.
+    pub fn with_options(defs: &IonStruct, options: PrototypeOptions) -> IonResult<Self> {
+        let defs: HashMap<&str, &IonValue> = defs.iter_fields().map(|(name, def)| (name.as_str(), def)).collect();
+        Prototypes::resolve_defs(defs, options)
+    }
+
+    /// Resolves the fields of every top-level struct in `docs` as one set of definitions, so
+    /// definitions can extend ones from other files. Defining the same name twice is an error.
+    pub fn from_documents(docs: &[IonDocument], options: PrototypeOptions) -> IonResult<Self> {
+        let mut defs = HashMap::new();
+        for st in docs.iter().flat_map(IonDocument::iter).filter_map(IonValue::as_struct) {
+            for (name, def) in st.iter_fields() {
+                if defs.insert(name.as_str(), def).is_some() {
+                    return Err(reference_error(format!("`{}` is defined more than once", name), &IonPath::root()));
+                }
+            }
+        }
+        Prototypes::resolve_defs(defs, options)
+    }
+
+    fn resolve_defs(defs: HashMap<&str, &IonValue>, options: PrototypeOptions) -> IonResult<Self> {
+        let earlier = HashMap::new();
+        let mut resolver = Resolver { defs: &defs, options: &options, earlier: &earlier, resolved: HashMap::new(), stack: Vec::new() };
+        let mut names: Vec<&str> = defs.keys().copied().collect();
+        // sorted so the reported error doesn't depend on hash order
+        names.sort_unstable();
+        for name in names {
+            resolver.resolve_def(name, &IonPath::root())?;
+        }
+        let resolved = resolver.resolved;
+        Ok(Prototypes { resolved, options })
+    }
+
+    /// Returns the resolved definition with the given name.
+    pub fn get(&self, name: &str) -> Option<&IonValue> { self.resolved.get(name) }
+
+    /// Returns an iterator over the names of all definitions.
+    pub fn names(&self) -> impl Iterator<Item = &String> { self.resolved.keys() }
+
+    /// Returns an `IonWalker` over the resolved definition with the given name, scoped to that name.
+    pub fn walker(&self, name: &str) -> IonResult<IonWalker<'_>> {
+        match self.resolved.get(name) {
+            Some(value) => Ok(IonWalker::with_scopes(value, vec![name.to_string()])),
+            None => Err(reference_error(format!("unknown prototype `{}`", name), &IonPath::root())),
+        }
+    }
+
+    /// Deserializes the resolved definition with the given name.
+    pub fn deserialize<T: IonDeserialize>(&self, name: &str) -> IonResult<T> {
+        T::deserialize(&self.walker(name)?)
+    }
+
+    /// Resolves `extends` fields in a value which isn't itself one of the definitions,
+    /// e.g. a level's enemy list.
+    pub fn resolve(&self, value: &IonValue) -> IonResult<IonValue> {
+        let defs = HashMap::new();
+        let mut resolver = Resolver { defs: &defs, options: &self.options, earlier: &self.resolved, resolved: HashMap::new(), stack: Vec::new() };
+        Ok(strip_deletes(&resolver.resolve_value(value, &IonPath::root())?, &self.options.merge))
+    }
+}
+
+fn reference_error(message: String, path: &IonPath) -> IonError {
+    IonError::new(IonErrorType::ReferenceError(message), path.to_scopes())
+}
+
+struct Resolver<'d, 'o> {
+    defs: &'d HashMap<&'d str, &'d IonValue>,
+    options: &'o PrototypeOptions,
+    /// Definitions resolved before this resolver was created, looked up before `defs`.
+    earlier: &'o HashMap<String, IonValue>,
+    resolved: HashMap<String, IonValue>,
+    /// Definitions currently being resolved, for cycle detection.
+    stack: Vec<String>,
+}
+
+impl<'d, 'o> Resolver<'d, 'o> {
+    fn resolve_def(&mut self, name: &str, path: &IonPath) -> IonResult<IonValue> {
+        if let Some(value) = self.earlier.get(name).or_else(|| self.resolved.get(name)) {
+            return Ok(value.clone());
+        }
+        if self.stack.iter().any(|n| n == name) {
+            let mut cycle = self.stack.clone();
+            cycle.push(name.to_string());
+            return Err(reference_error(format!("inheritance cycle: {}", cycle.join(" -> ")), path));
+        }
+        let def = match self.defs.get(name) {
+            Some(def) => *def,
+            None => return Err(reference_error(format!("unknown prototype `{}`", name), path)),
+        };
+        self.stack.push(name.to_string());
+        let value = self.resolve_value(def, &IonPath::root().field(name));
+        self.stack.pop();
+        // markers with nothing underneath them (e.g. in a definition without parents) aren't
+        // removed by merging
+        let value = strip_deletes(&value?, &self.options.merge);
+        self.resolved.insert(name.to_string(), value.clone());
+        Ok(value)
+    }
+
+    /// Returns the parent names listed in the `extends` field's value.
+    fn parents<'v>(&self, extends: &'v IonValue, path: &IonPath) -> IonResult<Vec<&'v str>> {
+        let invalid = || reference_error(format!("`{}` must be a name or a list of names", self.options.field), path);
+        match extends {
+            IonValue::List(list, _) => list.iter().map(|p| p.as_text().ok_or_else(invalid)).collect(),
+            other => Ok(vec![other.as_text().ok_or_else(invalid)?]),
+        }
+    }
+
+    fn resolve_value(&mut self, value: &IonValue, path: &IonPath) -> IonResult<IonValue> {
+        match value {
+            IonValue::Struct(st, ann) => {
+                let mut child = IonStruct::new_empty();
+                for (name, field) in st.iter_fields().filter(|(name, _)| **name != self.options.field) {
+                    child.insert(name.clone(), self.resolve_value(field, &path.field(name))?);
+                }
+                let child = IonValue::Struct(child, ann.clone());
+                let extends = match st.field(&self.options.field) {
+                    Some(extends) => extends,
+                    None => return Ok(child),
+                };
+                let extends_path = path.field(&self.options.field);
+                let mut base = IonValue::Struct(IonStruct::new_empty(), Annotations::new());
+                for name in self.parents(extends, &extends_path)? {
+                    let parent = self.resolve_def(name, &extends_path)?;
+                    if !parent.is(IonType::Struct) {
+                        return Err(reference_error(format!("can't extend {:?} `{}`", parent.ty(), name), &extends_path));
+                    }
+                    base = merge(&base, &parent, &self.options.merge).map_err(|e| rescope(e, path))?;
+                }
+                merge(&base, &child, &self.options.merge).map_err(|e| rescope(e, path))
+            }
+            IonValue::List(list, ann) => {
+                let mut items = Vec::with_capacity(list.len());
+                for (i, item) in list.iter().enumerate() {
+                    items.push(self.resolve_value(item, &path.index(i))?);
+                }
+                Ok(IonValue::List(IonList { items }, ann.clone()))
+            }
+            other => Ok(other.clone()),
+        }
+    }
+}
+
+/// Prefixes the scopes of a merge error, which are relative to the merged struct, with its path.
+fn rescope(mut e: IonError, path: &IonPath) -> IonError {
+    let mut scopes = path.to_scopes();
+    scopes.append(&mut e.scopes);
+    e.scopes = scopes;
+    e
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use super::*;
+    use crate::test_util::value;
+
+    fn prototypes() -> Prototypes {
+        let defs = value(r#"{
+            base_enemy: { hp: 10, speed: 1.0e0, loot: { gold: 5 } },
+            goblin: { extends: "base_enemy", hp: 6, loot: { dagger: 1 } },
+            goblin_chief: { extends: goblin, hp: 20, speed: delete::null },
+        }"#);
+        Prototypes::new(defs.as_struct().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn merges_parents_under_children() {
+        let prototypes = prototypes();
+        assert_eq!(prototypes.get("goblin"), Some(&value("{ hp: 6, speed: 1.0e0, loot: { gold: 5, dagger: 1 } }")));
+        assert_eq!(prototypes.get("goblin_chief"), Some(&value("{ hp: 20, loot: { gold: 5, dagger: 1 } }")));
+    }
+
+    #[test]
+    fn resolves_other_values() {
+        let level = value("{ enemies: [{ extends: goblin, hp: 3 }, { extends: [base_enemy, goblin_chief] }] }");
+        let resolved = prototypes().resolve(&level).unwrap();
+        let expected = value("{ enemies: [{ hp: 3, speed: 1.0e0, loot: { gold: 5, dagger: 1 } }, { hp: 20, speed: 1.0e0, loot: { gold: 5, dagger: 1 } }] }");
+        assert_eq!(resolved, expected);
+    }
+
+    #[test]
+    fn drops_delete_markers_without_parents() {
+        let defs = value(r#"{
+            base: { hp: 10, speed: delete::null, loot: { gold: 5, dagger: delete::null } },
+            child: { extends: base, loot: { gold: delete::null } },
+        }"#);
+        let prototypes = Prototypes::new(defs.as_struct().unwrap()).unwrap();
+        assert_eq!(prototypes.get("base"), Some(&value("{ hp: 10, loot: { gold: 5 } }")));
+        assert_eq!(prototypes.get("child"), Some(&value("{ hp: 10, loot: {} }")));
+        let resolved = prototypes.resolve(&value("{ enemies: [{ extends: base }], boss: delete::null }")).unwrap();
+        assert_eq!(resolved, value("{ enemies: [{ hp: 10, loot: { gold: 5 } }] }"));
+    }
+
+    #[rstest(text, message,
+        case("{ a: { extends: b }, b: { extends: a } }", "inheritance cycle: a -> b -> a"),
+        case("{ a: { extends: missing } }", "unknown prototype `missing`"),
+        case("{ a: 1, b: { extends: a } }", "can't extend"),
+        case("{ a: { extends: 1 } }", "must be a name or a list of names"),
+    )]
+    fn reports_reference_errors(text: &str, message: &str) {
+        match Prototypes::new(value(text).as_struct().unwrap()) {
+            Err(IonError { ty: IonErrorType::ReferenceError(msg), .. }) => assert!(msg.contains(message), "{}", msg),
+            other => panic!("expected a reference error, got {:?}", other),
+        }
+    }
+}
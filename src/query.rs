@@ -0,0 +1,313 @@
+//! Path expressions for pulling nested values out of an `IonValue`.
+//!
+//! A query is a sequence of steps applied to the set of values matched so far:
+//!
+//! | Step              | Matches                                                  |
+//! |-------------------|----------------------------------------------------------|
+//! | `name`, `.name`   | the field `name` of a struct                             |
+//! | `["any text"]`    | a field whose name isn't a plain identifier              |
+//! | `[2]`, `[-1]`     | a list element, counting from the end if negative        |
+//! | `[*]`             | every element of a list                                  |
+//! | `.*`              | every field of a struct                                  |
+//! | `..name`          | every field `name` at any depth below the current value  |
+//! | `..*`             | every value at any depth below the current value         |
+//! | `@ann`            | keeps only values annotated with `ann`                   |
+//!
+//! For example `animations[*].frames[0].duration` or `shapes[*]@circle.radius`.
+
+use crate::error::{IonError, IonResult};
+use crate::path::IonPath;
+use crate::types::{IonValue, Symbol};
+
+#[derive(Debug, Clone, PartialEq)]
+/// A value matched by a query, along with where it was found.
+pub struct QueryMatch<'v> {
+    pub path: IonPath,
+    pub value: &'v IonValue,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Step {
+    Field(String),
+    Index(i64),
+    AnyElement,
+    AnyField,
+    Descendants(Option<String>),
+    Annotation(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// A parsed path expression which can be run against any number of values.
+pub struct IonQuery {
+    steps: Vec<Step>,
+}
+
+impl IonQuery {
+    /// Parses a path expression. See the module documentation for the syntax.
+    pub fn parse(expr: &str) -> IonResult<IonQuery> {
+        Parser { expr, pos: 0 }.parse()
+    }
+
+    /// Runs this query against `root`, returning every match in document order.
+    /// Struct fields are visited in name order, since `IonStruct` doesn't preserve field order.
+    pub fn run<'v>(&self, root: &'v IonValue) -> Vec<QueryMatch<'v>> {
+        let mut current = vec![QueryMatch { path: IonPath::root(), value: root }];
+        for step in self.steps.iter() {
+            let mut next = Vec::new();
+            for m in current {
+                apply_step(step, m, &mut next);
+            }
+            current = next;
+        }
+        current
+    }
+}
+
+impl IonValue {
+    /// Parses and runs a path expression against this value. See the `query` module for the syntax.
+    pub fn query(&self, expr: &str) -> IonResult<Vec<QueryMatch<'_>>> {
+        Ok(IonQuery::parse(expr)?.run(self))
+    }
+}
+
+fn sorted_fields(value: &IonValue) -> Vec<(&Symbol, &IonValue)> {
+    let mut fields: Vec<_> = match value.as_struct() {
+        Some(st) => st.iter_fields().collect(),
+        None => Vec::new(),
+    };
+    fields.sort_by(|a, b| a.0.cmp(b.0));
+    fields
+}
+
+fn apply_step<'v>(step: &Step, m: QueryMatch<'v>, out: &mut Vec<QueryMatch<'v>>) {
+    match step {
+        Step::Field(name) => {
+            if let Some(value) = m.value.as_struct().and_then(|st| st.field(name)) {
+                out.push(QueryMatch { path: m.path.field(name), value });
+            }
+        }
+        Step::Index(i) => {
+            if let Some(list) = m.value.as_list() {
+                let idx = if *i < 0 { list.len() as i64 + i } else { *i };
+                if let Some(value) = usize::try_from(idx).ok().and_then(|idx| list.at(idx)) {
+                    out.push(QueryMatch { path: m.path.index(idx as usize), value });
+                }
+            }
+        }
+        Step::AnyElement => {
+            if let Some(list) = m.value.as_list() {
+                for (i, value) in list.iter().enumerate() {
+                    out.push(QueryMatch { path: m.path.index(i), value });
+                }
+            }
+        }
+        Step::AnyField => {
+            for (name, value) in sorted_fields(m.value) {
+                out.push(QueryMatch { path: m.path.field(name), value });
+            }
+        }
+        Step::Descendants(name) => descendants(name.as_deref(), &m.path, m.value, out),
+        Step::Annotation(ann) => {
+            if m.value.has_annotation(ann) {
+                out.push(m);
+            }
+        }
+    }
+}
+
+/// Collects every value below `value` in pre-order, or only fields called `name` if given.
+fn descendants<'v>(name: Option<&str>, path: &IonPath, value: &'v IonValue, out: &mut Vec<QueryMatch<'v>>) {
+    match value {
+        IonValue::List(list, _) => {
+            for (i, item) in list.iter().enumerate() {
+                let path = path.index(i);
+                if name.is_none() {
+                    out.push(QueryMatch { path: path.clone(), value: item });
+                }
+                descendants(name, &path, item, out);
+            }
+        }
+        IonValue::Struct(_, _) => {
+            for (field, item) in sorted_fields(value) {
+                let path = path.field(field);
+                if name.is_none() || name == Some(field.as_str()) {
+                    out.push(QueryMatch { path: path.clone(), value: item });
+                }
+                descendants(name, &path, item, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+struct Parser<'e> {
+    expr: &'e str,
+    pos: usize,
+}
+
+impl<'e> Parser<'e> {
+    fn error(&self, message: &str) -> IonError {
+        IonError::syntax(self.expr, self.pos, message)
+    }
+
+    fn rest(&self) -> &'e str { &self.expr[self.pos..] }
+
+    fn eat(&mut self, prefix: &str) -> bool {
+        if self.rest().starts_with(prefix) {
+            self.pos += prefix.len();
+            true
+        }
+        else {
+            false
+        }
+    }
+
+    fn identifier(&mut self) -> Option<String> {
+        let rest = self.rest();
+        let len = rest.find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$')).unwrap_or(rest.len());
+        if len == 0 { return None; }
+        self.pos += len;
+        Some(rest[..len].to_string())
+    }
+
+    /// Parses a double- or single-quoted name, supporting backslash escapes of the quote.
+    fn quoted(&mut self) -> IonResult<String> {
+        let quote = match self.rest().chars().next() {
+            Some(c @ ('"' | '\'')) => c,
+            _ => return Err(self.error("expected a quoted field name")),
+        };
+        self.pos += 1;
+        let mut name = String::new();
+        let mut chars = self.rest().char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '\\' => match chars.next() {
+                    Some((_, escaped)) => name.push(escaped),
+                    None => break,
+                },
+                c if c == quote => {
+                    self.pos += i + 1;
+                    return Ok(name);
+                }
+                c => name.push(c),
+            }
+        }
+        self.pos = self.expr.len();
+        Err(self.error("unterminated field name"))
+    }
+
+    fn parse(mut self) -> IonResult<IonQuery> {
+        let mut steps = Vec::new();
+        // a leading field name doesn't need a dot
+        if let Some(name) = self.identifier() {
+            steps.push(Step::Field(name));
+        }
+        while self.pos < self.expr.len() {
+            let step = if self.eat("..") {
+                if self.eat("*") { Step::Descendants(None) }
+                else {
+                    let name = match self.identifier() {
+                        Some(name) => name,
+                        None => self.quoted()?,
+                    };
+                    Step::Descendants(Some(name))
+                }
+            }
+            else if self.eat(".") {
+                if self.eat("*") { Step::AnyField }
+                else {
+                    match self.identifier() {
+                        Some(name) => Step::Field(name),
+                        None => Step::Field(self.quoted()?),
+                    }
+                }
+            }
+            else if self.eat("[") {
+                let step = if self.eat("*") { Step::AnyElement }
+                else if self.rest().starts_with(&['"', '\''][..]) { Step::Field(self.quoted()?) }
+                else {
+                    let rest = self.rest();
+                    let len = rest.find(|c: char| !(c.is_ascii_digit() || c == '-')).unwrap_or(rest.len());
+                    match rest[..len].parse::<i64>() {
+                        Ok(i) => {
+                            self.pos += len;
+                            Step::Index(i)
+                        }
+                        Err(_) => return Err(self.error("expected an index, `*` or a quoted field name")),
+                    }
+                };
+                if !self.eat("]") {
+                    return Err(self.error("expected `]`"));
+                }
+                step
+            }
+            else if self.eat("@") {
+                match self.identifier() {
+                    Some(ann) => Step::Annotation(ann),
+                    None => Step::Annotation(self.quoted()?),
+                }
+            }
+            else {
+                return Err(self.error("expected `.`, `..`, `[` or `@`"));
+            };
+            steps.push(step);
+        }
+        Ok(IonQuery { steps })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use super::*;
+    use crate::reader::IonReader;
+
+    const ANIMATIONS: &str = r#"{
+        animations: [
+            { name: walk, frames: [{ duration: 0.1 }, { duration: 0.2 }] },
+            { name: "jump", frames: [{ duration: 0.5 }] },
+        ],
+        shapes: [circle::{ radius: 2 }, rect::{ w: 1, h: 3 }, circle::{ radius: 4 }],
+        "odd key": 7,
+    }"#;
+
+    fn paths(expr: &str) -> Vec<String> {
+        let root = IonReader::read_string(ANIMATIONS).unwrap().into_single().unwrap();
+        root.query(expr).unwrap().iter().map(|m| m.path.to_string()).collect()
+    }
+
+    #[rstest(expr, expected,
+        case("animations[0].name", &["animations[0].name"]),
+        case("animations[-1].frames[0].duration", &["animations[1].frames[0].duration"]),
+        case("animations[*].frames[*].duration", &["animations[0].frames[0].duration", "animations[0].frames[1].duration", "animations[1].frames[0].duration"]),
+        case("shapes[*]@circle.radius", &["shapes[0].radius", "shapes[2].radius"]),
+        case("..duration", &["animations[0].frames[0].duration", "animations[0].frames[1].duration", "animations[1].frames[0].duration"]),
+        case("animations[1].*", &["animations[1].frames", "animations[1].name"]),
+        case(r#"["odd key"]"#, &[r#"["odd key"]"#]),
+        case("animations[5]", &[]),
+        case("shapes.radius", &[]),
+    )]
+    fn matches_paths(expr: &str, expected: &[&str]) {
+        assert_eq!(paths(expr), expected);
+    }
+
+    #[test]
+    fn returns_matched_values() {
+        let root = IonReader::read_string(ANIMATIONS).unwrap().into_single().unwrap();
+        let radii: Vec<i64> = root.query("shapes[*]@circle.radius").unwrap().iter()
+            .filter_map(|m| m.value.as_int())
+            .collect();
+        assert_eq!(radii, vec![2, 4]);
+    }
+
+    #[rstest(expr,
+        case("animations["),
+        case("animations[x]"),
+        case("animations[0"),
+        case(r#"["unterminated"#),
+        case("animations!"),
+    )]
+    fn rejects_invalid_expression(expr: &str) {
+        assert!(IonQuery::parse(expr).is_err(), "{} should not parse", expr);
+    }
+}
@@ -1,56 +1,210 @@
+use std::any::Any;
+use std::borrow::Cow;
 use std::collections::HashMap;
-use std::path::Path;
+use std::io::Read;
+use std::panic::AssertUnwindSafe;
+use std::path::{Path, PathBuf};
+use bytes::Bytes;
 use ion_c_sys::*;
 use ion_c_sys::reader::{IonCReader, IonCReaderHandle};
 use num_bigint::Sign;
+use crate::anchors::{resolve_refs, AnchorOptions};
+use crate::borrowed::IonValueRef;
+use crate::document::IonDocument;
 use crate::error::{IonError, IonErrorType, IonResult};
-use crate::types::{IonList, IonStruct, IonValue};
+use crate::include::IncludeResolver;
+#[cfg(feature = "mmap")]
+use crate::mapped::IonMappedFile;
+use crate::migrate::Migrations;
+use crate::symbol::SymbolTable;
+use crate::types::{Annotations, IonList, IonStruct, IonValue, Symbol};
+
+#[derive(Debug, Clone)]
+/// Optional processing done by `IonReader::read_file_with` and friends.
+pub struct ReadOptions {
+    /// Replace values annotated with `include_annotation`, like `include::"common/palette.ion"`,
+    /// with the contents of the named file. Relative paths are looked up next to the including
+    /// file (or in the current directory when reading a string), then in `search_path`.
+    pub includes: bool,
+    pub include_annotation: String,
+    pub search_path: Vec<PathBuf>,
+    /// Replace references like `ref::walk_frame` with the value anchored as
+    /// `anchor::walk_frame::...`. Resolved after includes, so anchors can come from other files.
+    pub references: bool,
+    pub anchors: AnchorOptions,
+    /// Bring the document up to the current version with these migrations, reading its version
+    /// from the first value. Applied last, so the steps see includes and references already
+    /// resolved.
+    pub migrations: Option<Migrations>,
+}
+
+impl Default for ReadOptions {
+    fn default() -> Self {
+        ReadOptions {
+            includes: false,
+            include_annotation: "include".to_string(),
+            search_path: Vec::new(),
+            references: false,
+            anchors: AnchorOptions::default(),
+            migrations: None,
+        }
+    }
+}
 
 pub struct IonReader;
 impl IonReader {
-    pub fn read_file(path: impl AsRef<Path>) -> IonResult<IonValue> {
-        let s = std::fs::read_to_string(path.as_ref())?;
-        IonReader::read_string(&s)
+    pub fn read_file(path: impl AsRef<Path>) -> IonResult<IonDocument> {
+        let bytes = std::fs::read(path.as_ref())?;
+        IonReader::read_buffer(&bytes)
+    }
+
+    /// Reads a file by mapping it into memory rather than copying it, which is worthwhile for
+    /// large binary files. See `IonMappedFile` for reading only part of one. Needs the `mmap`
+    /// feature.
+    #[cfg(feature = "mmap")]
+    pub fn read_file_mapped(path: impl AsRef<Path>) -> IonResult<IonDocument> {
+        IonMappedFile::open(path)?.read()
+    }
+
+    /// Reads a file, then applies the processing enabled in `options`.
+    pub fn read_file_with(path: impl AsRef<Path>, options: &ReadOptions) -> IonResult<IonDocument> {
+        let path = path.as_ref();
+        let doc = IonReader::read_file(path)?;
+        let dir = path.parent().unwrap_or_else(|| Path::new("."));
+        IonReader::process(doc, Some(path), dir, options)
+    }
+
+    /// Reads a string, then applies the processing enabled in `options`.
+    pub fn read_string_with(string: &str, options: &ReadOptions) -> IonResult<IonDocument> {
+        IonReader::process(IonReader::read_string(string)?, None, Path::new("."), options)
+    }
+
+    /// Reads a buffer containing either text or binary Ion, then applies the processing enabled
+    /// in `options`.
+    pub fn read_buffer_with(buf: &[u8], options: &ReadOptions) -> IonResult<IonDocument> {
+        IonReader::process(IonReader::read_buffer(buf)?, None, Path::new("."), options)
+    }
+
+    /// Reads from `reader` until it's exhausted, then applies the processing enabled in `options`.
+    pub fn read_from_with<R: Read>(reader: R, options: &ReadOptions) -> IonResult<IonDocument> {
+        IonReader::process(IonReader::read_from(reader)?, None, Path::new("."), options)
+    }
+
+    fn process(doc: IonDocument, file: Option<&Path>, dir: &Path, options: &ReadOptions) -> IonResult<IonDocument> {
+        let mut doc = doc;
+        if options.includes {
+            doc = IncludeResolver::new(options, file).resolve(doc, dir)?;
+        }
+        if options.references {
+            // anchors are shared by every top-level value, so resolve them all together
+            let values = IonValue::List(IonList { items: doc.into_values() }, Annotations::new());
+            doc = match resolve_refs(&values, &options.anchors)? {
+                IonValue::List(list, _) => IonDocument::new(list.items),
+                other => IonDocument::new(vec![other]),
+            };
+        }
+        if let Some(migrations) = &options.migrations {
+            doc = migrations.migrate_document(doc)?;
+        }
+        Ok(doc)
+    }
+
+    pub fn read_string(string: &str) -> IonResult<IonDocument> {
+        IonReader::read_all(&mut IonCReaderHandle::try_from(string)?, None)
+    }
+
+    /// Reads a buffer containing either text or binary Ion.
+    pub fn read_buffer(buf: &[u8]) -> IonResult<IonDocument> {
+        IonReader::read_all(&mut IonCReaderHandle::try_from(buf)?, None)
+    }
+
+    /// Reads text or binary Ion from any `io::Read`, like stdin or an entry in an archive.
+    /// Ion C pulls the input in chunks of `STREAM_CHUNK_SIZE` bytes as it parses, so only the
+    /// values read so far are held in memory, not all of the input.
+    pub fn read_from<R: Read>(reader: R) -> IonResult<IonDocument> {
+        let mut input = Box::new(StreamInput {
+            reader,
+            chunk: vec![0; STREAM_CHUNK_SIZE].into_boxed_slice(),
+            error: None,
+            panic: None,
+        });
+        // ion-c-sys only opens readers over buffers, so one is opened over a blank buffer and its
+        // reader swapped for a stream reader, which the handle then closes when dropped
+        let mut handle = IonCReaderHandle::try_from(" ")?;
+        let mut stream_reader = std::ptr::null_mut();
+        // Ion C may already read from `input` while opening the stream, so it can fail here too
+        let result = ionc!(ion_reader_open_stream(
+            &mut stream_reader,
+            &mut *input as *mut StreamInput<R> as *mut std::os::raw::c_void,
+            Some(fill_stream::<R>),
+            &mut ION_READER_OPTIONS::default(),
+        )).map_err(IonError::from).and_then(|()| {
+            let buffer_reader = std::mem::replace(&mut *handle, stream_reader);
+            ionc!(ion_reader_close(buffer_reader))?;
+            IonReader::read_all(&mut handle, None)
+        });
+        drop(handle);
+        if let Some(payload) = input.panic.take() {
+            std::panic::resume_unwind(payload);
+        }
+        match input.error.take() {
+            Some(e) => Err(e.into()),
+            None => result,
+        }
+    }
+
+    /// Reads text or binary Ion from a shared buffer, without copying it first. Blobs in binary
+    /// input are slices of `bytes` rather than copies (except small ones, which `Bytes` stores
+    /// inline); blobs in text input are base64, so they're decoded into buffers of their own.
+    pub fn read_bytes(bytes: Bytes) -> IonResult<IonDocument> {
+        IonReader::read_all(&mut IonCReaderHandle::try_from(&bytes[..])?, Some(&bytes))
     }
 
-    pub fn read_string(string: &str) -> IonResult<IonValue> {
+    fn read_all(reader: &mut IonCReaderHandle, source: Option<&Bytes>) -> IonResult<IonDocument> {
+        let mut symbols = SymbolTable::new();
         let mut items = Vec::new();
-        let mut reader = IonCReaderHandle::try_from(string)?;
         loop {
             let ty = reader.next()?;
             if ty == ION_TYPE_NONE || ty == ION_TYPE_EOF {
-                return Ok(IonValue::List(IonList { items }, Vec::new()));
+                return Ok(IonDocument::new(items));
             }
             else {
-                items.push(IonReader::read_value(&mut reader)?);
+                items.push(IonReader::read_value(reader, &mut symbols, source)?);
             }
         }
     }
 
-    fn read_value(reader: &mut IonCReaderHandle) -> IonResult<IonValue> {
-        let annotations = reader.get_annotations()?
-            .iter()
-            .map(|ann| ann.to_string())
-            .collect();
+    /// Reads the current value's annotations, sharing their text through `symbols`.
+    pub(crate) fn read_annotations(reader: &mut IonCReaderHandle, symbols: &mut SymbolTable) -> IonResult<Annotations> {
+        Ok(reader.get_annotations()?.iter().map(|ann| symbols.intern(ann)).collect())
+    }
+
+    /// Reads the current value's field name, sharing its text through `symbols`.
+    pub(crate) fn read_field_name(reader: &mut IonCReaderHandle, symbols: &mut SymbolTable) -> IonResult<Symbol> {
+        Ok(symbols.intern(reader.get_field_name()?.as_str()))
+    }
+
+    /// Reads the current value. Blobs are sliced out of `source` where it holds the binary Ion
+    /// being read, and copied otherwise.
+    pub(crate) fn read_value(reader: &mut IonCReaderHandle, symbols: &mut SymbolTable, source: Option<&Bytes>) -> IonResult<IonValue> {
+        let annotations = IonReader::read_annotations(reader, symbols)?;
+        // typed nulls like `null.int` are reported with their type
+        if reader.is_null()? {
+            return Ok(IonValue::Null(annotations));
+        }
         match reader.get_type()? {
             ION_TYPE_NULL => Ok(IonValue::Null(annotations)),
             ION_TYPE_SEXP => Err(IonError::new(IonErrorType::TypeNotSupported("SExpr"), Vec::new())),
-            ION_TYPE_BLOB => Err(IonError::new(IonErrorType::TypeNotSupported("Blob"), Vec::new())),
             ION_TYPE_CLOB => Err(IonError::new(IonErrorType::TypeNotSupported("Clob"), Vec::new())),
-            ION_TYPE_SYMBOL => Err(IonError::new(IonErrorType::TypeNotSupported("Symbol"), Vec::new())),
             ION_TYPE_DATAGRAM => Err(IonError::new(IonErrorType::TypeNotSupported("Datagram"), Vec::new())),
-            ION_TYPE_STRUCT => Ok(IonValue::Struct(IonReader::read_struct(reader)?, annotations)),
-            ION_TYPE_LIST => Ok(IonValue::List(IonReader::read_list(reader)?, annotations)),
+            ION_TYPE_BLOB => Ok(IonValue::Blob(IonReader::read_blob(reader, source)?, annotations)),
+            ION_TYPE_STRUCT => Ok(IonValue::Struct(IonReader::read_struct(reader, symbols, source)?, annotations)),
+            ION_TYPE_LIST => Ok(IonValue::List(IonReader::read_list(reader, symbols, source)?, annotations)),
             ION_TYPE_STRING => Ok(IonValue::String(reader.read_string()?.as_str().to_string(), annotations)),
+            ION_TYPE_SYMBOL => Ok(IonValue::Symbol(symbols.intern(reader.read_string()?.as_str()), annotations)),
             ION_TYPE_INT => Ok(IonValue::Integer(reader.read_i64()?, annotations)),
             ION_TYPE_FLOAT => Ok(IonValue::Float(reader.read_f64()?, annotations)),
-            ION_TYPE_DECIMAL => {
-                let (bigint, exp) = reader.read_bigdecimal()?.into_bigint_and_exponent();
-                let coeff = bigint.iter_u64_digits().next().unwrap() as i64;
-                let coeff = if bigint.sign() == Sign::Minus { -coeff } else { coeff };
-                let value = coeff as f64 / (10f64).powi(exp as i32);
-                Ok(IonValue::Float(value, annotations))
-            }
+            ION_TYPE_DECIMAL => Ok(IonValue::Float(IonReader::read_decimal(reader)?, annotations)),
             ION_TYPE_BOOL => Ok(IonValue::Boolean(reader.read_bool()?, annotations)),
             ION_TYPE_TIMESTAMP => {
                 Ok(IonValue::Timestamp(reader.read_datetime()?.as_datetime().clone(), annotations))
@@ -59,7 +213,24 @@ impl IonReader {
         }
     }
 
-    fn read_struct(reader: &mut IonCReaderHandle) -> IonResult<IonStruct> {
+    fn read_decimal(reader: &mut IonCReaderHandle) -> IonResult<f64> {
+        let (bigint, exp) = reader.read_bigdecimal()?.into_bigint_and_exponent();
+        let coeff = bigint.iter_u64_digits().next().unwrap() as i64;
+        let coeff = if bigint.sign() == Sign::Minus { -coeff } else { coeff };
+        Ok(coeff as f64 / (10f64).powi(exp as i32))
+    }
+
+    fn read_blob(reader: &mut IonCReaderHandle, source: Option<&Bytes>) -> IonResult<Bytes> {
+        if let Some(source) = source {
+            if let Some(content) = binary_lob(reader, source)? {
+                return Ok(source.slice_ref(content));
+            }
+        }
+        // Ion C decodes lobs into a fresh buffer, which the `Bytes` takes over without copying
+        Ok(Bytes::from(reader.read_bytes()?))
+    }
+
+    fn read_struct(reader: &mut IonCReaderHandle, symbols: &mut SymbolTable, source: Option<&Bytes>) -> IonResult<IonStruct> {
         reader.step_in()?;
         let mut fields = HashMap::new();
         loop {
@@ -70,15 +241,15 @@ impl IonReader {
                     return Ok(IonStruct::new(fields));
                 }
                 _ => {
-                    let key = reader.get_field_name()?.as_str().to_string();
-                    let value = IonReader::read_value(reader)?;
+                    let key = IonReader::read_field_name(reader, symbols)?;
+                    let value = IonReader::read_value(reader, symbols, source)?;
                     fields.insert(key, value);
                 }
             }
         }
     }
 
-    fn read_list(reader: &mut IonCReaderHandle) -> IonResult<IonList> {
+    fn read_list(reader: &mut IonCReaderHandle, symbols: &mut SymbolTable, source: Option<&Bytes>) -> IonResult<IonList> {
         reader.step_in()?;
         let mut items = Vec::new();
         loop {
@@ -89,10 +260,304 @@ impl IonReader {
                     return Ok(IonList { items });
                 }
                 _ => {
-                    let item = IonReader::read_value(reader)?;
+                    let item = IonReader::read_value(reader, symbols, source)?;
                     items.push(item);
                 }
             }
         }
     }
-}
\ No newline at end of file
+
+    /// Reads a buffer containing either text or binary Ion into values whose strings borrow from
+    /// `buf` rather than being copied, where `buf` is binary Ion. Ion C decodes text Ion (and
+    /// binary symbols, which are stored as IDs) into its own memory, so those are copied as
+    /// usual. Blobs are borrowed from binary input the same way. Returns the top-level values in
+    /// order.
+    pub fn read_borrowed(buf: &[u8]) -> IonResult<Vec<IonValueRef<'_>>> {
+        let mut reader = IonCReaderHandle::try_from(buf)?;
+        let mut symbols = SymbolTable::new();
+        let mut items = Vec::new();
+        loop {
+            let ty = reader.next()?;
+            if ty == ION_TYPE_NONE || ty == ION_TYPE_EOF {
+                return Ok(items);
+            }
+            else {
+                items.push(IonReader::read_value_ref(&mut reader, buf, &mut symbols)?);
+            }
+        }
+    }
+
+    fn read_value_ref<'a>(reader: &mut IonCReaderHandle, buf: &'a [u8], symbols: &mut SymbolTable) -> IonResult<IonValueRef<'a>> {
+        let annotations = IonReader::read_annotations(reader, symbols)?;
+        if reader.is_null()? {
+            return Ok(IonValueRef::Null(annotations));
+        }
+        match reader.get_type()? {
+            ION_TYPE_NULL => Ok(IonValueRef::Null(annotations)),
+            ION_TYPE_SEXP => Err(IonError::new(IonErrorType::TypeNotSupported("SExpr"), Vec::new())),
+            ION_TYPE_CLOB => Err(IonError::new(IonErrorType::TypeNotSupported("Clob"), Vec::new())),
+            ION_TYPE_DATAGRAM => Err(IonError::new(IonErrorType::TypeNotSupported("Datagram"), Vec::new())),
+            ION_TYPE_BLOB => match binary_lob(reader, buf)? {
+                Some(content) => Ok(IonValueRef::Blob(Cow::Borrowed(content), annotations)),
+                None => Ok(IonValueRef::Blob(Cow::Owned(reader.read_bytes()?), annotations)),
+            },
+            ION_TYPE_STRUCT => {
+                reader.step_in()?;
+                let mut fields = HashMap::new();
+                loop {
+                    reader.next()?;
+                    match reader.get_type()? {
+                        ION_TYPE_NONE | ION_TYPE_EOF => break,
+                        _ => {
+                            let key = IonReader::read_field_name(reader, symbols)?;
+                            fields.insert(key, IonReader::read_value_ref(reader, buf, symbols)?);
+                        }
+                    }
+                }
+                reader.step_out()?;
+                Ok(IonValueRef::Struct(fields, annotations))
+            }
+            ION_TYPE_LIST => {
+                reader.step_in()?;
+                let mut items = Vec::new();
+                loop {
+                    reader.next()?;
+                    match reader.get_type()? {
+                        ION_TYPE_NONE | ION_TYPE_EOF => break,
+                        _ => items.push(IonReader::read_value_ref(reader, buf, symbols)?),
+                    }
+                }
+                reader.step_out()?;
+                Ok(IonValueRef::List(items, annotations))
+            }
+            ION_TYPE_STRING => Ok(IonValueRef::String(read_borrowed_string(reader, buf)?, annotations)),
+            // binary symbols are stored as IDs, so their text always comes from Ion C
+            ION_TYPE_SYMBOL => Ok(IonValueRef::Symbol(Cow::Owned(reader.read_string()?.as_str().to_string()), annotations)),
+            ION_TYPE_INT => Ok(IonValueRef::Integer(reader.read_i64()?, annotations)),
+            ION_TYPE_FLOAT => Ok(IonValueRef::Float(reader.read_f64()?, annotations)),
+            ION_TYPE_DECIMAL => Ok(IonValueRef::Float(IonReader::read_decimal(reader)?, annotations)),
+            ION_TYPE_BOOL => Ok(IonValueRef::Boolean(reader.read_bool()?, annotations)),
+            ION_TYPE_TIMESTAMP => {
+                Ok(IonValueRef::Timestamp(*reader.read_datetime()?.as_datetime(), annotations))
+            }
+            _ => unreachable!()
+        }
+    }
+}
+
+/// How many bytes `IonReader::read_from` reads from its input at a time.
+const STREAM_CHUNK_SIZE: usize = 64 * 1024;
+
+/// The input of a stream reader opened by `IonReader::read_from`.
+struct StreamInput<R> {
+    reader: R,
+    /// Holds the latest chunk read until Ion C copies it into its own pages.
+    chunk: Box<[u8]>,
+    /// The error `reader` failed with, if it did.
+    error: Option<std::io::Error>,
+    /// What `reader` panicked with, if it did, to be resumed once Ion C has returned.
+    panic: Option<Box<dyn Any + Send>>,
+}
+
+/// Called by Ion C whenever a stream reader runs out of input, to point `stream` at the next
+/// chunk of its `StreamInput<R>`. Panics in `R::read` are caught here, since they mustn't
+/// unwind through Ion C.
+unsafe extern "C" fn fill_stream<R: Read>(stream: *mut _ion_user_stream) -> iERR {
+    let stream = &mut *stream;
+    let input = &mut *(stream.handler_state as *mut StreamInput<R>);
+    if input.error.is_some() || input.panic.is_some() {
+        return ion_error_code_IERR_READ_ERROR;
+    }
+    loop {
+        let StreamInput { reader, chunk, .. } = &mut *input;
+        let read = match std::panic::catch_unwind(AssertUnwindSafe(|| reader.read(chunk))) {
+            Ok(read) => read,
+            Err(payload) => {
+                input.panic = Some(payload);
+                return ion_error_code_IERR_READ_ERROR;
+            }
+        };
+        match read {
+            Ok(0) => {
+                stream.limit = std::ptr::null_mut();
+                return ion_error_code_IERR_EOF;
+            }
+            Ok(len) => {
+                stream.curr = input.chunk.as_mut_ptr();
+                stream.limit = stream.curr.add(len);
+                return ion_error_code_IERR_OK;
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => {
+                input.error = Some(e);
+                return ion_error_code_IERR_READ_ERROR;
+            }
+        }
+    }
+}
+
+/// The version marker every binary Ion stream starts with.
+const BINARY_MARKER: [u8; 4] = [0xE0, 0x01, 0x00, 0xEA];
+
+/// Returns the content of the reader's current value (the bytes after its type descriptor and
+/// length) within `buf`, or `None` if `buf` is text. Ion C reports where the value starts,
+/// including any annotation wrapper, so the headers are skipped here; callers should check the
+/// result against the length Ion C reports for the value.
+fn binary_content<'a>(reader: &IonCReaderHandle, buf: &'a [u8]) -> Option<&'a [u8]> {
+    if !buf.starts_with(&BINARY_MARKER) { return None; }
+    let mut offset: POSITION = 0;
+    ionc!(ion_reader_get_value_offset(**reader, &mut offset)).ok()?;
+    let offset = usize::try_from(offset).ok()?;
+    let (mut start, mut len) = binary_header(buf, offset)?;
+    if buf[offset] >> 4 == 0xE {
+        // skip past the annotation symbols to the wrapped value
+        let (annotations_len, after) = read_var_uint(buf, start)?;
+        (start, len) = binary_header(buf, after.checked_add(annotations_len)?)?;
+    }
+    buf.get(start..start.checked_add(len)?)
+}
+
+/// Returns the current lob's bytes within `buf`, or `None` if they can't be located there, e.g.
+/// because `buf` is text.
+fn binary_lob<'a>(reader: &mut IonCReaderHandle, buf: &'a [u8]) -> IonResult<Option<&'a [u8]>> {
+    let content = match binary_content(reader, buf) {
+        Some(content) => content,
+        None => return Ok(None),
+    };
+    let mut size: SIZE = 0;
+    ionc!(ion_reader_get_lob_size(**reader, &mut size))?;
+    Ok(Some(content).filter(|content| usize::try_from(size).ok() == Some(content.len())))
+}
+
+/// Reads the type descriptor at `pos`, returning where the value's content starts and its length.
+fn binary_header(buf: &[u8], pos: usize) -> Option<(usize, usize)> {
+    match buf.get(pos)? & 0x0F {
+        14 => read_var_uint(buf, pos + 1).map(|(len, start)| (start, len)),
+        15 => Some((pos + 1, 0)),
+        len => Some((pos + 1, len as usize)),
+    }
+}
+
+/// Reads a binary Ion `VarUInt` at `pos`, returning its value and the position after it.
+fn read_var_uint(buf: &[u8], mut pos: usize) -> Option<(usize, usize)> {
+    let mut value = 0usize;
+    loop {
+        let byte = *buf.get(pos)?;
+        pos += 1;
+        value = value.checked_mul(0x80)? | usize::from(byte & 0x7F);
+        if byte & 0x80 != 0 { return Some((value, pos)); }
+    }
+}
+
+/// Returns the current string borrowed from `buf` if it's stored there as is, or a copy of
+/// what Ion C decoded. Ion C copies every string it reads, so only binary input, where the
+/// string's bytes can be located from the value's offset, can be borrowed from.
+fn read_borrowed_string<'a>(reader: &mut IonCReaderHandle, buf: &'a [u8]) -> IonResult<Cow<'a, str>> {
+    let content = binary_content(reader, buf);
+    let text = reader.read_string()?;
+    match content.filter(|bytes| *bytes == text.as_bytes()).and_then(|bytes| std::str::from_utf8(bytes).ok()) {
+        Some(borrowed) => Ok(Cow::Borrowed(borrowed)),
+        None => Ok(Cow::Owned(text.as_str().to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::walker::IonWalker;
+    use crate::writer::IonWriter;
+
+    #[test]
+    fn reads_blobs_from_shared_buffers() {
+        let doc = IonReader::read_bytes(Bytes::from_static(b"{ name: hero, pixels: {{aGVsbG8=}}, empty: {{}} }")).unwrap();
+        let walker = IonWalker::new(&doc.values()[0]);
+        let pixels: Bytes = walker.get_type("pixels").unwrap();
+        assert_eq!(&pixels[..], b"hello");
+        assert_eq!(walker.get_blob("empty").unwrap(), b"");
+        assert!(walker.enter("name").unwrap().as_bytes().is_err());
+    }
+
+    #[test]
+    fn slices_blobs_out_of_binary_input() {
+        // `Bytes` stores slices of up to 31 bytes inline, so only longer blobs point into the input
+        let text = "{ long: {{MDEyMzQ1Njc4OWFiY2RlZjAxMjM0NTY3ODlhYmNkZWYwMTIzNDU2Nzg5YWJjZGVm}}, tagged: [png::{{MDEyMzQ1Njc4OWFiY2RlZjAxMjM0NTY3ODlhYmNkZWYwMTIzNDU2Nzg5YWJjZGVm}}], short: {{aGVsbG8=}}, empty: {{}} }";
+        let source = Bytes::from(IonWriter::write_binary(IonReader::read_string(text).unwrap().values()).unwrap());
+        let in_source = |blob: &Bytes| source.as_ptr_range().contains(&blob.as_ptr());
+        let doc = IonReader::read_bytes(source.clone()).unwrap();
+        assert_eq!(doc, IonReader::read_string(text).unwrap());
+        let walker = IonWalker::new(&doc.values()[0]);
+        assert!(in_source(&walker.get_type::<Bytes>("long").unwrap()));
+        assert!(in_source(&walker.enter("tagged").unwrap().enter_index(0).unwrap().as_bytes().unwrap()));
+        assert_eq!(walker.get_blob("short").unwrap(), b"hello");
+
+        // text blobs are base64, so they're decoded rather than sliced
+        let source = Bytes::from(text);
+        let doc = IonReader::read_bytes(source.clone()).unwrap();
+        let long: Bytes = IonWalker::new(&doc.values()[0]).get_type("long").unwrap();
+        assert!(!source.as_ptr_range().contains(&long.as_ptr()));
+    }
+
+    #[test]
+    fn reads_typed_nulls_as_null() {
+        let doc = IonReader::read_string("null.int tag::null.struct [null.string]").unwrap();
+        assert_eq!(doc.values()[0], IonValue::Null(Annotations::new()));
+        assert_eq!(doc.values()[1], IonValue::Null(Annotations::from(vec!["tag"])));
+        assert_eq!(doc.values()[2], IonValue::List(IonList { items: vec![IonValue::Null(Annotations::new())] }, Annotations::new()));
+        assert_eq!(IonReader::read_borrowed(b"null.symbol").unwrap(), vec![IonValueRef::Null(Annotations::new())]);
+    }
+
+    #[test]
+    fn reads_from_io() {
+        let doc = IonReader::read_from(&b"1 two \"three\""[..]).unwrap();
+        assert_eq!(doc.values().len(), 3);
+        assert_eq!(doc.values()[1], IonValue::Symbol(Symbol::new("two"), Annotations::new()));
+        let binary = IonWriter::write_binary(doc.values()).unwrap();
+        assert_eq!(IonReader::read_from(&binary[..]).unwrap(), doc);
+    }
+
+    /// Hands out its input a few bytes at a time, then fails if `fail` is set.
+    struct Trickle<'a> {
+        input: &'a [u8],
+        fail: bool,
+    }
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if self.input.is_empty() && self.fail {
+                return Err(std::io::Error::new(std::io::ErrorKind::BrokenPipe, "closed"));
+            }
+            let len = buf.len().min(self.input.len()).min(3);
+            buf[..len].copy_from_slice(&self.input[..len]);
+            self.input = &self.input[len..];
+            Ok(len)
+        }
+    }
+
+    #[test]
+    fn reads_from_io_in_chunks() {
+        let text = format!("{{ name: hero, frames: [{}] }} 2", vec!["{ duration: 1 }"; 10_000].join(", "));
+        let expected = IonReader::read_string(&text).unwrap();
+        assert_eq!(IonReader::read_from(Trickle { input: text.as_bytes(), fail: false }).unwrap(), expected);
+        let binary = IonWriter::write_binary(expected.values()).unwrap();
+        assert_eq!(IonReader::read_from(Trickle { input: &binary, fail: false }).unwrap(), expected);
+
+        match IonReader::read_from(Trickle { input: b"1 [2, 3", fail: true }) {
+            Err(IonError { ty: IonErrorType::IoError(e), .. }) => assert_eq!(e.kind(), std::io::ErrorKind::BrokenPipe),
+            other => panic!("expected an I/O error, got {:?}", other),
+        }
+    }
+
+    struct Exploding;
+
+    impl Read for Exploding {
+        fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+            panic!("exploded");
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "exploded")]
+    fn resumes_panics_from_io_after_ion_c_returns() {
+        let _ = IonReader::read_from(Exploding);
+    }
+}
@@ -0,0 +1,796 @@
+//! Validation of `IonValue`s against [Ion Schema](https://amazon-ion.github.io/ion-schema/) types.
+//!
+//! Supports ISL 1.0 and 2.0 documents made of `type::{...}` definitions using the `type`,
+//! `fields`, `element`, `occurs`, `one_of`, `valid_values`, `range`, `annotations`, `regex` and
+//! `codepoint_length` constraints. Schema imports are not supported.
+//!
+//! Since decimals are read as floats, the `decimal` and `float` types are interchangeable.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use chrono::{DateTime, FixedOffset};
+use regex::{Regex, RegexBuilder};
+use crate::error::{IonError, IonErrorType, IonResult};
+use crate::path::IonPath;
+use crate::reader::IonReader;
+use crate::types::{IonStruct, IonType, IonValue, Symbol};
+
+#[derive(Debug, Clone, PartialEq)]
+/// A single way in which a value failed to match a schema type.
+pub struct Violation {
+    /// Location of the offending value inside the validated value.
+    pub path: IonPath,
+    /// Name of the constraint that failed, e.g. `fields` or `range`.
+    pub constraint: &'static str,
+    pub message: String,
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {} ({})", self.path, self.message, self.constraint)
+    }
+}
+
+/// A set of named types loaded from an Ion Schema document.
+pub struct IonSchema {
+    types: HashMap<String, TypeDef>,
+}
+
+impl IonSchema {
+    /// Loads a schema from an ISL file.
+    pub fn read_file(path: impl AsRef<Path>) -> IonResult<IonSchema> {
+        IonSchema::from_values(IonReader::read_file(path)?.values())
+    }
+
+    /// Loads a schema from ISL text.
+    pub fn read_string(text: &str) -> IonResult<IonSchema> {
+        IonSchema::from_values(IonReader::read_string(text)?.values())
+    }
+
+    /// Loads a schema from a single ISL value, or a list of them.
+    pub fn from_value(document: &IonValue) -> IonResult<IonSchema> {
+        match document.as_list() {
+            Some(list) => IonSchema::from_values(&list.items),
+            None => IonSchema::from_values(std::slice::from_ref(document)),
+        }
+    }
+
+    /// Loads a schema from the given top-level ISL values.
+    pub fn from_values(values: &[IonValue]) -> IonResult<IonSchema> {
+        let mut types = HashMap::new();
+        for value in values {
+            let st = match value {
+                IonValue::Struct(st, _) => st,
+                // version markers and open content
+                _ => continue,
+            };
+            if value.has_annotation("schema_header") {
+                if st.field("imports").is_some() {
+                    return Err(schema_error("schema imports are not supported", &["schema_header".to_string()]));
+                }
+            }
+            else if value.has_annotation("type") {
+                let name = match st.field("name").and_then(IonValue::as_text) {
+                    Some(name) => name.to_string(),
+                    None => return Err(schema_error("type definition is missing a `name`", &[])),
+                };
+                let scopes = vec![name.clone()];
+                let def = parse_type_def(st, &scopes)?;
+                if types.insert(name, def).is_some() {
+                    return Err(schema_error("type is defined more than once", &scopes));
+                }
+            }
+        }
+        let schema = IonSchema { types };
+        schema.check_references()?;
+        Ok(schema)
+    }
+
+    /// Returns the names of all types defined by this schema.
+    pub fn type_names(&self) -> impl Iterator<Item = &str> {
+        self.types.keys().map(String::as_str)
+    }
+
+    /// Returns true if this schema defines a type with the given name.
+    pub fn has_type(&self, name: &str) -> bool {
+        self.types.contains_key(name)
+    }
+
+    /// Validates `value` against the named type, returning every violation found.
+    /// An empty list means the value is valid.
+    pub fn validate(&self, type_name: &str, value: &IonValue) -> IonResult<Vec<Violation>> {
+        let mut violations = Vec::new();
+        let ty = TypeRef::Named(type_name.to_string());
+        if !self.has_type(type_name) && builtin_matches(type_name, value).is_none() {
+            return Err(schema_error(format!("unknown type `{}`", type_name), &[]));
+        }
+        self.check_ref(&ty, value, &IonPath::root(), &mut violations);
+        Ok(violations)
+    }
+
+    /// Returns true if `value` is valid for the named type.
+    pub fn is_valid(&self, type_name: &str, value: &IonValue) -> IonResult<bool> {
+        Ok(self.validate(type_name, value)?.is_empty())
+    }
+
+    /// Makes sure every referenced type exists and no type is defined in terms of itself.
+    fn check_references(&self) -> IonResult<()> {
+        for (name, def) in self.types.iter() {
+            let mut refs = Vec::new();
+            def.collect_refs(&mut refs);
+            for r in refs {
+                if !self.types.contains_key(r) && !is_builtin(r) {
+                    return Err(schema_error(format!("unknown type `{}`", r), std::slice::from_ref(name)));
+                }
+            }
+            // `type` and `one_of` references are checked against the same value, so following
+            // them must never lead back to this type
+            let mut pending = Vec::new();
+            def.collect_direct_refs(&mut pending);
+            let mut seen = HashSet::new();
+            while let Some(current) = pending.pop() {
+                if current == name.as_str() {
+                    return Err(schema_error("type is defined in terms of itself", std::slice::from_ref(name)));
+                }
+                if seen.insert(current) {
+                    if let Some(def) = self.types.get(current) {
+                        def.collect_direct_refs(&mut pending);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn check_ref(&self, ty: &TypeRef, value: &IonValue, path: &IonPath, out: &mut Vec<Violation>) {
+        match ty {
+            TypeRef::Nullable(_) if value.is(IonType::Null) => {}
+            TypeRef::Nullable(inner) => self.check_ref(inner, value, path, out),
+            TypeRef::Inline(def) => self.check_def(def, value, path, out),
+            TypeRef::Named(name) => {
+                if let Some(def) = self.types.get(name) {
+                    self.check_def(def, value, path, out);
+                }
+                else if builtin_matches(name, value) == Some(false) {
+                    violation(out, path, "type", format!("expected {}, found {}", name, type_name(value)));
+                }
+            }
+        }
+    }
+
+    fn check_def(&self, def: &TypeDef, value: &IonValue, path: &IonPath, out: &mut Vec<Violation>) {
+        for constraint in def.constraints.iter() {
+            self.check_constraint(constraint, value, path, out);
+        }
+    }
+
+    fn check_constraint(&self, constraint: &Constraint, value: &IonValue, path: &IonPath, out: &mut Vec<Violation>) {
+        match constraint {
+            Constraint::Type(ty) => self.check_ref(ty, value, path, out),
+            Constraint::Fields { fields, closed } => {
+                let st = match value.as_struct() {
+                    Some(st) => st,
+                    None => return violation(out, path, "fields", format!("expected a struct, found {}", type_name(value))),
+                };
+                for (name, field) in fields.iter() {
+                    let found = st.field(name);
+                    let count = found.is_some() as usize;
+                    if !field.occurs.contains(count) {
+                        out.push(Violation {
+                            path: path.field(name),
+                            constraint: "occurs",
+                            message: match count {
+                                0 => format!("missing required field `{}`", name),
+                                _ => format!("field `{}` must not occur", name),
+                            },
+                        });
+                    }
+                    if let Some(found) = found {
+                        self.check_ref(&field.ty, found, &path.field(name), out);
+                    }
+                }
+                if *closed {
+                    let mut unexpected: Vec<&Symbol> = st.iter_fields()
+                        .map(|(name, _)| name)
+                        .filter(|name| !fields.iter().any(|(declared, _)| declared == *name))
+                        .collect();
+                    unexpected.sort();
+                    for name in unexpected {
+                        out.push(Violation {
+                            path: path.field(name),
+                            constraint: "fields",
+                            message: format!("unexpected field `{}`", name),
+                        });
+                    }
+                }
+            }
+            Constraint::Element(ty) => match value {
+                IonValue::List(list, _) => {
+                    for (i, item) in list.iter().enumerate() {
+                        self.check_ref(ty, item, &path.index(i), out);
+                    }
+                }
+                IonValue::Struct(st, _) => {
+                    for (name, field) in st.iter_fields() {
+                        self.check_ref(ty, field, &path.field(name), out);
+                    }
+                }
+                _ => violation(out, path, "element", format!("expected a container, found {}", type_name(value))),
+            },
+            Constraint::OneOf(types) => {
+                let matched = types.iter()
+                    .filter(|ty| {
+                        let mut violations = Vec::new();
+                        self.check_ref(ty, value, path, &mut violations);
+                        violations.is_empty()
+                    })
+                    .count();
+                match matched {
+                    1 => {}
+                    0 => violation(out, path, "one_of", "value matches none of the types"),
+                    n => violation(out, path, "one_of", format!("value matches {} of the types, expected exactly one", n)),
+                }
+            }
+            Constraint::ValidValues(valid) => {
+                let mut bare = value.clone();
+                bare.annotations_mut().clear();
+                let matched = valid.iter().any(|v| match v {
+                    ValidValue::Value(v) => *v == bare,
+                    ValidValue::Range(range) => range.contains(value) == Some(true),
+                });
+                if !matched {
+                    violation(out, path, "valid_values", "value is not one of the valid values");
+                }
+            }
+            Constraint::Range(range) => match range.contains(value) {
+                Some(true) => {}
+                Some(false) => violation(out, path, "range", format!("value is outside of {}", range)),
+                None => violation(out, path, "range", format!("expected a number or timestamp, found {}", type_name(value))),
+            },
+            Constraint::Annotations(expected) => {
+                let actual = value.annotations();
+                for (name, required) in expected.annotations.iter() {
+                    if *required && !value.has_annotation(name) {
+                        violation(out, path, "annotations", format!("missing required annotation `{}`", name));
+                    }
+                }
+                if expected.closed {
+                    for ann in actual.iter() {
+                        if !expected.annotations.iter().any(|(name, _)| name == ann) {
+                            violation(out, path, "annotations", format!("unexpected annotation `{}`", ann));
+                        }
+                    }
+                }
+                if expected.ordered {
+                    let positions: Vec<usize> = actual.iter()
+                        .filter_map(|ann| expected.annotations.iter().position(|(name, _)| name == ann))
+                        .collect();
+                    if positions.windows(2).any(|w| w[0] > w[1]) {
+                        violation(out, path, "annotations", "annotations are out of order");
+                    }
+                }
+            }
+            Constraint::Regex(regex) => match value.as_text() {
+                Some(text) if regex.is_match(text) => {}
+                Some(_) => violation(out, path, "regex", format!("value does not match /{}/", regex.as_str())),
+                None => violation(out, path, "regex", format!("expected text, found {}", type_name(value))),
+            },
+            Constraint::CodepointLength(length) => match value.as_text() {
+                Some(text) => {
+                    let count = text.chars().count();
+                    if !length.contains(count) {
+                        violation(out, path, "codepoint_length", format!("length {} is outside of {}", count, length));
+                    }
+                }
+                None => violation(out, path, "codepoint_length", format!("expected text, found {}", type_name(value))),
+            },
+        }
+    }
+}
+
+fn violation(out: &mut Vec<Violation>, path: &IonPath, constraint: &'static str, message: impl Into<String>) {
+    out.push(Violation { path: path.clone(), constraint, message: message.into() });
+}
+
+fn schema_error(message: impl Into<String>, scopes: &[String]) -> IonError {
+    IonError::new(IonErrorType::InvalidSchema(message.into()), scopes.to_vec())
+}
+
+fn scoped(scopes: &[String], scope: impl Into<String>) -> Vec<String> {
+    let mut scopes = scopes.to_vec();
+    scopes.push(scope.into());
+    scopes
+}
+
+/// Describes the type of `value` for messages.
+fn type_name(value: &IonValue) -> String {
+    format!("{:?}", value.ty()).to_lowercase()
+}
+
+const BUILTIN_TYPES: &[&str] = &[
+    "any", "nothing", "bool", "int", "float", "decimal", "number", "string", "symbol", "text",
+    "timestamp", "blob", "clob", "lob", "list", "sexp", "struct",
+];
+
+fn is_builtin(name: &str) -> bool {
+    name == "$any" || name == "$null" || BUILTIN_TYPES.contains(&name.strip_prefix('$').unwrap_or(name))
+}
+
+/// Checks `value` against a built-in type. Returns `None` if `name` isn't a built-in type.
+/// `$`-prefixed variants also accept null.
+fn builtin_matches(name: &str, value: &IonValue) -> Option<bool> {
+    use IonValue::*;
+    if name == "$null" { return Some(matches!(value, Null(_))); }
+    if name == "$any" { return Some(true); }
+    let (base, nullable) = match name.strip_prefix('$') {
+        Some(base) => (base, true),
+        None => (name, false),
+    };
+    let matched = match (base, value) {
+        ("any", Null(_)) => false,
+        ("any", _) => true,
+        ("nothing", _) => false,
+        ("bool", Boolean(..)) => true,
+        ("int", Integer(..)) => true,
+        ("float" | "decimal", Float(..)) => true,
+        ("number", Integer(..) | Float(..)) => true,
+        ("string" | "text", String(..)) => true,
+        ("symbol" | "text", Symbol(..)) => true,
+        ("timestamp", Timestamp(..)) => true,
+        ("blob" | "lob", Blob(..)) => true,
+        ("list", List(..)) => true,
+        ("struct", Struct(..)) => true,
+        (base, _) if BUILTIN_TYPES.contains(&base) => false,
+        _ => return None,
+    };
+    Some(matched || (nullable && matches!(value, Null(_))))
+}
+
+enum TypeRef {
+    Named(String),
+    Inline(Box<TypeDef>),
+    Nullable(Box<TypeRef>),
+}
+
+impl TypeRef {
+    fn collect_refs<'a>(&'a self, out: &mut Vec<&'a str>) {
+        match self {
+            TypeRef::Named(name) => out.push(name),
+            TypeRef::Inline(def) => def.collect_refs(out),
+            TypeRef::Nullable(inner) => inner.collect_refs(out),
+        }
+    }
+
+    /// Like `collect_refs`, but only collects the types checked against the referring value
+    /// itself, not against its fields or elements.
+    fn collect_direct_refs<'a>(&'a self, out: &mut Vec<&'a str>) {
+        match self {
+            TypeRef::Named(name) => out.push(name),
+            TypeRef::Inline(def) => def.collect_direct_refs(out),
+            TypeRef::Nullable(inner) => inner.collect_direct_refs(out),
+        }
+    }
+}
+
+struct TypeDef {
+    constraints: Vec<Constraint>,
+}
+
+impl TypeDef {
+    fn collect_refs<'a>(&'a self, out: &mut Vec<&'a str>) {
+        for constraint in self.constraints.iter() {
+            match constraint {
+                Constraint::Type(ty) | Constraint::Element(ty) => ty.collect_refs(out),
+                Constraint::OneOf(types) => {
+                    for ty in types.iter() {
+                        ty.collect_refs(out);
+                    }
+                }
+                Constraint::Fields { fields, .. } => {
+                    for (_, field) in fields.iter() {
+                        field.ty.collect_refs(out);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Collects the named types this definition checks the value itself against, through its
+    /// `type` and `one_of` constraints.
+    fn collect_direct_refs<'a>(&'a self, out: &mut Vec<&'a str>) {
+        for constraint in self.constraints.iter() {
+            match constraint {
+                Constraint::Type(ty) => ty.collect_direct_refs(out),
+                Constraint::OneOf(types) => {
+                    for ty in types.iter() {
+                        ty.collect_direct_refs(out);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+enum Constraint {
+    Type(TypeRef),
+    Fields { fields: Vec<(String, FieldDef)>, closed: bool },
+    Element(TypeRef),
+    OneOf(Vec<TypeRef>),
+    ValidValues(Vec<ValidValue>),
+    Range(ValueRange),
+    Annotations(AnnotationsConstraint),
+    Regex(Regex),
+    CodepointLength(LengthRange),
+}
+
+struct FieldDef {
+    ty: TypeRef,
+    occurs: LengthRange,
+}
+
+enum ValidValue {
+    Value(IonValue),
+    Range(ValueRange),
+}
+
+struct AnnotationsConstraint {
+    /// Annotation names and whether each is required.
+    annotations: Vec<(String, bool)>,
+    closed: bool,
+    ordered: bool,
+}
+
+/// An inclusive range of non-negative integers, used for lengths and `occurs`.
+struct LengthRange {
+    min: usize,
+    max: Option<usize>,
+}
+
+impl LengthRange {
+    fn contains(&self, n: usize) -> bool {
+        n >= self.min && self.max.map(|max| n <= max).unwrap_or(true)
+    }
+}
+
+impl Display for LengthRange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.max {
+            Some(max) if max == self.min => write!(f, "{}", max),
+            Some(max) => write!(f, "[{}, {}]", self.min, max),
+            None => write!(f, "[{}, max]", self.min),
+        }
+    }
+}
+
+enum Bound {
+    Unbounded,
+    Number { value: f64, exclusive: bool },
+    Timestamp { value: DateTime<FixedOffset>, exclusive: bool },
+}
+
+/// A range of numbers or timestamps, used by `range` and `valid_values`.
+struct ValueRange {
+    min: Bound,
+    max: Bound,
+}
+
+impl ValueRange {
+    /// Returns whether `value` is inside this range, or `None` if it isn't comparable with it.
+    fn contains(&self, value: &IonValue) -> Option<bool> {
+        let above_min = match (&self.min, value) {
+            (Bound::Unbounded, _) => true,
+            (Bound::Number { value: min, exclusive }, _) => {
+                let v = value.as_float()?;
+                if *exclusive { v > *min } else { v >= *min }
+            }
+            (Bound::Timestamp { value: min, exclusive }, IonValue::Timestamp(v, _)) => {
+                if *exclusive { v > min } else { v >= min }
+            }
+            _ => return None,
+        };
+        let below_max = match (&self.max, value) {
+            (Bound::Unbounded, _) => true,
+            (Bound::Number { value: max, exclusive }, _) => {
+                let v = value.as_float()?;
+                if *exclusive { v < *max } else { v <= *max }
+            }
+            (Bound::Timestamp { value: max, exclusive }, IonValue::Timestamp(v, _)) => {
+                if *exclusive { v < max } else { v <= max }
+            }
+            _ => return None,
+        };
+        Some(above_min && below_max)
+    }
+}
+
+impl Display for ValueRange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let bound = |b: &Bound, name: &str| match b {
+            Bound::Unbounded => name.to_string(),
+            Bound::Number { value, exclusive: true } => format!("exclusive::{}", value),
+            Bound::Number { value, exclusive: false } => value.to_string(),
+            Bound::Timestamp { value, exclusive: true } => format!("exclusive::{}", value.to_rfc3339()),
+            Bound::Timestamp { value, exclusive: false } => value.to_rfc3339(),
+        };
+        write!(f, "[{}, {}]", bound(&self.min, "min"), bound(&self.max, "max"))
+    }
+}
+
+fn parse_type_def(st: &IonStruct, scopes: &[String]) -> IonResult<TypeDef> {
+    // ISL 1.0 closes the `fields` constraint with a separate `content: closed`
+    let content_closed = st.field("content").and_then(IonValue::as_text) == Some("closed");
+    let mut fields: Vec<_> = st.iter_fields().collect();
+    fields.sort_by(|a, b| a.0.cmp(b.0));
+    let mut constraints = Vec::new();
+    for (key, value) in fields {
+        let scopes = scoped(scopes, key);
+        let constraint = match key.as_str() {
+            // handled by the containing type or field
+            "name" | "content" | "occurs" => continue,
+            "type" => Constraint::Type(parse_type_ref(value, &scopes)?),
+            "fields" => parse_fields(value, content_closed, &scopes)?,
+            "element" => Constraint::Element(parse_type_ref(value, &scopes)?),
+            "one_of" => parse_one_of(value, &scopes)?,
+            "valid_values" => parse_valid_values(value, &scopes)?,
+            "range" => Constraint::Range(parse_range(value, &scopes)?),
+            "annotations" => parse_annotations(value, &scopes)?,
+            "regex" => parse_regex(value, &scopes)?,
+            "codepoint_length" => Constraint::CodepointLength(parse_length(value, &scopes)?),
+            other => return Err(schema_error(format!("unsupported constraint `{}`", other), &scopes)),
+        };
+        constraints.push(constraint);
+    }
+    Ok(TypeDef { constraints })
+}
+
+fn parse_type_ref(value: &IonValue, scopes: &[String]) -> IonResult<TypeRef> {
+    let ty = match value {
+        IonValue::Symbol(name, _) => TypeRef::Named(name.to_string()),
+        IonValue::Struct(st, _) => TypeRef::Inline(Box::new(parse_type_def(st, scopes)?)),
+        _ => return Err(schema_error("expected a type name or an inline type definition", scopes)),
+    };
+    if value.has_annotation("nullable") || value.has_annotation("$null_or") {
+        Ok(TypeRef::Nullable(Box::new(ty)))
+    }
+    else {
+        Ok(ty)
+    }
+}
+
+fn parse_fields(value: &IonValue, content_closed: bool, scopes: &[String]) -> IonResult<Constraint> {
+    let st = value.as_struct().ok_or_else(|| schema_error("expected a struct of field types", scopes))?;
+    let mut fields = Vec::new();
+    for (name, field) in st.iter_fields() {
+        let scopes = scoped(scopes, name);
+        let occurs = match field.as_struct().and_then(|st| st.field("occurs")) {
+            Some(occurs) => parse_occurs(occurs, &scopes)?,
+            None => LengthRange { min: 0, max: Some(1) },
+        };
+        fields.push((name.to_string(), FieldDef { ty: parse_type_ref(field, &scopes)?, occurs }));
+    }
+    fields.sort_by(|a, b| a.0.cmp(&b.0));
+    let closed = content_closed || value.has_annotation("closed");
+    Ok(Constraint::Fields { fields, closed })
+}
+
+fn parse_one_of(value: &IonValue, scopes: &[String]) -> IonResult<Constraint> {
+    let list = value.as_list().ok_or_else(|| schema_error("expected a list of types", scopes))?;
+    let types = list.iter().map(|item| parse_type_ref(item, scopes)).collect::<IonResult<_>>()?;
+    Ok(Constraint::OneOf(types))
+}
+
+fn parse_occurs(value: &IonValue, scopes: &[String]) -> IonResult<LengthRange> {
+    match value.as_symbol() {
+        Some("optional") => Ok(LengthRange { min: 0, max: Some(1) }),
+        Some("required") => Ok(LengthRange { min: 1, max: Some(1) }),
+        _ => parse_length(value, scopes),
+    }
+}
+
+fn parse_valid_values(value: &IonValue, scopes: &[String]) -> IonResult<Constraint> {
+    let list = value.as_list().ok_or_else(|| schema_error("expected a list of values", scopes))?;
+    let mut valid = Vec::new();
+    for item in list.iter() {
+        if item.has_annotation("range") {
+            valid.push(ValidValue::Range(parse_range(item, scopes)?));
+        }
+        else {
+            valid.push(ValidValue::Value(item.clone()));
+        }
+    }
+    Ok(Constraint::ValidValues(valid))
+}
+
+fn parse_bound(value: &IonValue, unbounded: &str, scopes: &[String]) -> IonResult<Bound> {
+    let exclusive = value.has_annotation("exclusive");
+    match value {
+        IonValue::Symbol(s, _) if s == unbounded => Ok(Bound::Unbounded),
+        IonValue::Integer(i, _) => Ok(Bound::Number { value: *i as f64, exclusive }),
+        IonValue::Float(f, _) => Ok(Bound::Number { value: *f, exclusive }),
+        IonValue::Timestamp(ts, _) => Ok(Bound::Timestamp { value: *ts, exclusive }),
+        _ => Err(schema_error(format!("expected a number, timestamp or `{}`", unbounded), scopes)),
+    }
+}
+
+fn range_bounds<'v>(value: &'v IonValue, scopes: &[String]) -> IonResult<(&'v IonValue, &'v IonValue)> {
+    match value.as_list_sized::<2>() {
+        Some([min, max]) => Ok((min, max)),
+        None => Err(schema_error("expected a range like `[min, max]`", scopes)),
+    }
+}
+
+fn parse_range(value: &IonValue, scopes: &[String]) -> IonResult<ValueRange> {
+    let (min, max) = range_bounds(value, scopes)?;
+    let range = ValueRange { min: parse_bound(min, "min", scopes)?, max: parse_bound(max, "max", scopes)? };
+    match (&range.min, &range.max) {
+        (Bound::Unbounded, Bound::Unbounded) =>
+            Err(schema_error("a range must have at least one bound", scopes)),
+        (Bound::Number { .. }, Bound::Timestamp { .. }) | (Bound::Timestamp { .. }, Bound::Number { .. }) =>
+            Err(schema_error("range bounds must both be numbers or both be timestamps", scopes)),
+        _ => Ok(range),
+    }
+}
+
+/// Parses an exact non-negative integer or an integer range.
+fn parse_length(value: &IonValue, scopes: &[String]) -> IonResult<LengthRange> {
+    let bound = |v: &IonValue, unbounded: &str| -> IonResult<Option<i64>> {
+        match v {
+            IonValue::Symbol(s, _) if s == unbounded => Ok(None),
+            IonValue::Integer(i, _) if *i >= 0 => Ok(Some(*i)),
+            _ => Err(schema_error(format!("expected a non-negative integer or `{}`", unbounded), scopes)),
+        }
+    };
+    if let IonValue::Integer(_, _) = value {
+        let n = bound(value, "")?.unwrap_or(0) as usize;
+        return Ok(LengthRange { min: n, max: Some(n) });
+    }
+    let (min, max) = range_bounds(value, scopes)?;
+    let lo = bound(min, "min")?.map(|n| if min.has_annotation("exclusive") { n + 1 } else { n });
+    let hi = bound(max, "max")?.map(|n| if max.has_annotation("exclusive") { n - 1 } else { n });
+    Ok(LengthRange { min: lo.unwrap_or(0) as usize, max: hi.map(|n| n.max(0) as usize) })
+}
+
+fn parse_annotations(value: &IonValue, scopes: &[String]) -> IonResult<Constraint> {
+    let list = value.as_list().ok_or_else(|| schema_error("expected a list of annotations", scopes))?;
+    let all_required = value.has_annotation("required");
+    let mut annotations = Vec::new();
+    for item in list.iter() {
+        let name = item.as_text().ok_or_else(|| schema_error("expected an annotation symbol", scopes))?;
+        let required = item.has_annotation("required") || (all_required && !item.has_annotation("optional"));
+        annotations.push((name.to_string(), required));
+    }
+    Ok(Constraint::Annotations(AnnotationsConstraint {
+        annotations,
+        closed: value.has_annotation("closed"),
+        ordered: value.has_annotation("ordered"),
+    }))
+}
+
+fn parse_regex(value: &IonValue, scopes: &[String]) -> IonResult<Constraint> {
+    let pattern = value.as_str().ok_or_else(|| schema_error("expected a regex string", scopes))?;
+    RegexBuilder::new(pattern)
+        .case_insensitive(value.has_annotation("i"))
+        .multi_line(value.has_annotation("m"))
+        .build()
+        .map(Constraint::Regex)
+        .map_err(|e| schema_error(format!("invalid regex: {}", e), scopes))
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use super::*;
+    use crate::test_util::value;
+
+    const SPRITES: &str = r#"
+        $ion_schema_2_0
+        schema_header::{}
+        type::{
+            name: sprite,
+            type: struct,
+            fields: closed::{
+                name: { type: string, occurs: required, codepoint_length: range::[1, 16] },
+                fps: { type: int, range: [1, 60] },
+                blend: { valid_values: [add, alpha] },
+                frames: { type: list, element: frame },
+            },
+        }
+        type::{
+            name: frame,
+            type: struct,
+            annotations: closed::[keyframe],
+            fields: { duration: { type: number, occurs: required, range: [exclusive::0, max] } },
+        }
+        schema_footer::{}
+    "#;
+
+    fn schema() -> IonSchema {
+        IonSchema::read_string(SPRITES).unwrap()
+    }
+
+    fn violations(text: &str) -> Vec<String> {
+        schema().validate("sprite", &value(text)).unwrap().iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn accepts_valid_value() {
+        let text = r#"{ name: "hero", fps: 12, blend: add, frames: [{ duration: 0.1 }, keyframe::{ duration: 2 }] }"#;
+        assert_eq!(violations(text), Vec::<String>::new());
+        assert!(schema().is_valid("sprite", &value(text)).unwrap());
+    }
+
+    #[rstest(text, expected,
+        case(r#"{ fps: 12 }"#, "name: missing required field `name` (occurs)"),
+        case(r#"{ name: "hero", speed: 3 }"#, "speed: unexpected field `speed` (fields)"),
+        case(r#"{ name: "hero", fps: 0 }"#, "fps: value is outside of [1, 60] (range)"),
+        case(r#"{ name: "hero", fps: "12" }"#, "fps: expected int, found string (type)"),
+        case(r#"{ name: "hero", blend: multiply }"#, "blend: value is not one of the valid values (valid_values)"),
+        case(r#"{ name: "" }"#, "name: length 0 is outside of [1, 16] (codepoint_length)"),
+        case(r#"{ name: "hero", frames: [{ duration: 0 }] }"#, "frames[0].duration: value is outside of [exclusive::0, max] (range)"),
+        case(r#"{ name: "hero", frames: [loop::{ duration: 1 }] }"#, "frames[0]: unexpected annotation `loop` (annotations)"),
+        case(r#"[]"#, "(root): expected a struct, found list (fields)"),
+    )]
+    fn reports_violation(text: &str, expected: &str) {
+        let found = violations(text);
+        assert!(found.iter().any(|v| v == expected), "{:?} doesn't contain {:?}", found, expected);
+    }
+
+    #[test]
+    fn open_content_allows_extra_fields() {
+        let found = schema().validate("frame", &value("{ duration: 1, easing: linear }")).unwrap();
+        assert!(found.is_empty(), "{:?}", found);
+    }
+
+    #[test]
+    fn one_of_requires_exactly_one_match() {
+        let schema = IonSchema::read_string("type::{ name: id, one_of: [int, number, string] }").unwrap();
+        assert!(schema.is_valid("id", &value(r#""a""#)).unwrap());
+        assert!(schema.is_valid("id", &value("1.5")).unwrap());
+        let found = schema.validate("id", &value("1")).unwrap();
+        assert_eq!(found[0].message, "value matches 2 of the types, expected exactly one");
+        assert!(!schema.is_valid("id", &value("true")).unwrap());
+    }
+
+    #[test]
+    fn nullable_field() {
+        let schema = IonSchema::read_string("type::{ name: t, fields: { a: $null_or::int } }").unwrap();
+        assert!(schema.is_valid("t", &value("{ a: null }")).unwrap());
+        assert!(!schema.is_valid("t", &value("{ a: true }")).unwrap());
+    }
+
+    #[rstest(isl, message,
+        case("type::{ name: a, type: missing }", "unknown type `missing`"),
+        case("type::{ name: a, type: b } type::{ name: b, type: a }", "type is defined in terms of itself"),
+        case("type::{ name: a, type: $null_or::a }", "type is defined in terms of itself"),
+        case("type::{ name: a, one_of: [int, a] }", "type is defined in terms of itself"),
+        case("type::{ name: a, one_of: [int, { type: b }] } type::{ name: b, type: a }", "type is defined in terms of itself"),
+        case("type::{ name: a } type::{ name: a }", "type is defined more than once"),
+        case("type::{ name: a, range: [min, max] }", "a range must have at least one bound"),
+        case("type::{ name: a, frobnicate: 1 }", "unsupported constraint `frobnicate`"),
+        case("schema_header::{ imports: [{ id: \"other.isl\" }] }", "schema imports are not supported"),
+    )]
+    fn rejects_invalid_schema(isl: &str, message: &str) {
+        match IonSchema::read_string(isl) {
+            Err(IonError { ty: IonErrorType::InvalidSchema(found), .. }) => assert_eq!(found, message),
+            other => panic!("expected an invalid schema error, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn recursive_types_through_fields() {
+        // as generated for an `Option<Box<Self>>` field
+        let isl = "type::{ name: node, type: struct, fields: { value: int, next: $null_or::node } }";
+        let schema = IonSchema::read_string(isl).unwrap();
+        assert!(schema.is_valid("node", &value("{ value: 1, next: { value: 2, next: null } }")).unwrap());
+        let found = schema.validate("node", &value("{ value: 1, next: { value: true } }")).unwrap();
+        assert_eq!(found[0].to_string(), "next.value: expected int, found boolean (type)");
+    }
+
+    #[test]
+    fn unknown_type_name_is_an_error() {
+        assert!(schema().validate("animation", &value("{}")).is_err());
+    }
+}
@@ -0,0 +1,234 @@
+use std::borrow::Borrow;
+use std::collections::HashSet;
+use std::fmt::{Debug, Display, Formatter};
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::sync::Arc;
+
+#[derive(Clone, PartialOrd, Ord)]
+/// A shared string used for struct field names, annotations and symbol values.
+///
+/// Cloning a `Symbol` is cheap, and field names, annotations and symbol values read from the
+/// same document share one allocation per distinct text (see `SymbolTable`), so repeated field
+/// names in large documents are only stored once.
+pub struct Symbol(Arc<str>);
+
+impl Symbol {
+    /// Create a symbol with the given text.
+    pub fn new(text: &str) -> Symbol { Symbol(Arc::from(text)) }
+
+    /// Returns the symbol's text.
+    pub fn as_str(&self) -> &str { &self.0 }
+}
+
+impl PartialEq for Symbol {
+    fn eq(&self, other: &Self) -> bool {
+        // symbols from the same document are usually the same allocation
+        Arc::ptr_eq(&self.0, &other.0) || self.0 == other.0
+    }
+}
+impl Eq for Symbol {}
+
+impl Hash for Symbol {
+    // must match `str`'s hash so maps keyed by `Symbol` can be looked up with a `&str`
+    fn hash<H: Hasher>(&self, state: &mut H) { self.as_str().hash(state) }
+}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool { self.as_str() == other }
+}
+impl PartialEq<&str> for Symbol {
+    fn eq(&self, other: &&str) -> bool { self.as_str() == *other }
+}
+impl PartialEq<String> for Symbol {
+    fn eq(&self, other: &String) -> bool { self.as_str() == other }
+}
+
+impl Deref for Symbol {
+    type Target = str;
+    fn deref(&self) -> &str { &self.0 }
+}
+impl Borrow<str> for Symbol {
+    fn borrow(&self) -> &str { &self.0 }
+}
+impl AsRef<str> for Symbol {
+    fn as_ref(&self) -> &str { &self.0 }
+}
+
+impl From<&str> for Symbol {
+    fn from(text: &str) -> Self { Symbol::new(text) }
+}
+impl From<&String> for Symbol {
+    fn from(text: &String) -> Self { Symbol::new(text) }
+}
+impl From<String> for Symbol {
+    fn from(text: String) -> Self { Symbol::new(&text) }
+}
+impl From<&Symbol> for Symbol {
+    fn from(symbol: &Symbol) -> Self { symbol.clone() }
+}
+
+impl From<Symbol> for String {
+    fn from(symbol: Symbol) -> Self { symbol.as_str().to_string() }
+}
+impl From<&Symbol> for String {
+    fn from(symbol: &Symbol) -> Self { symbol.as_str().to_string() }
+}
+impl PartialEq<Symbol> for String {
+    fn eq(&self, other: &Symbol) -> bool { self == other.as_str() }
+}
+
+impl Display for Symbol {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result { f.write_str(&self.0) }
+}
+impl Debug for Symbol {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result { Debug::fmt(&*self.0, f) }
+}
+
+#[derive(Debug, Default)]
+/// Deduplicates the field names, annotations and symbol values read from one document. Each
+/// reader owns its own table and drops it when it's done, so nothing is kept alive past the
+/// values using it and no lock is shared between readers.
+pub(crate) struct SymbolTable(HashSet<Arc<str>>);
+
+impl SymbolTable {
+    pub(crate) fn new() -> Self { SymbolTable::default() }
+
+    /// Returns a symbol with the given text, sharing the allocation of an earlier one if there is
+    /// one.
+    pub(crate) fn intern(&mut self, text: &str) -> Symbol {
+        match self.0.get(text) {
+            Some(existing) => Symbol(existing.clone()),
+            None => {
+                let arc: Arc<str> = Arc::from(text);
+                self.0.insert(arc.clone());
+                Symbol(arc)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Default, PartialEq, Eq, Hash)]
+/// The annotations on an `IonValue`.
+///
+/// Most values have none, so they're stored out of line and an empty set doesn't allocate:
+/// it's the size of a single pointer.
+// boxed rather than a plain `Vec` to keep this to one word in every `IonValue`
+#[allow(clippy::box_collection)]
+pub struct Annotations(Option<Box<Vec<Symbol>>>);
+
+impl Annotations {
+    /// Create an empty set of annotations.
+    pub fn new() -> Self { Annotations(None) }
+
+    /// Adds an annotation to the end.
+    pub fn push(&mut self, annotation: impl Into<Symbol>) {
+        self.0.get_or_insert_with(Default::default).push(annotation.into());
+    }
+
+    /// Removes the annotation at `index` and returns it.
+    pub fn remove(&mut self, index: usize) -> Symbol {
+        let list = self.0.as_mut().expect("index out of bounds");
+        let removed = list.remove(index);
+        if list.is_empty() { self.0 = None; }
+        removed
+    }
+
+    /// Replaces the annotation at `index`, keeping its position, and returns the old one.
+    pub fn set(&mut self, index: usize, annotation: impl Into<Symbol>) -> Symbol {
+        let list = self.0.as_mut().expect("index out of bounds");
+        std::mem::replace(&mut list[index], annotation.into())
+    }
+
+    /// Removes every annotation.
+    pub fn clear(&mut self) { self.0 = None; }
+
+    /// Returns true if one of the annotations is `annotation`.
+    pub fn contains(&self, annotation: &str) -> bool {
+        self.iter().any(|ann| ann.as_str() == annotation)
+    }
+
+    /// Returns the annotations as a slice.
+    pub fn as_slice(&self) -> &[Symbol] {
+        match &self.0 {
+            Some(list) => list,
+            None => &[],
+        }
+    }
+}
+
+impl Deref for Annotations {
+    type Target = [Symbol];
+    fn deref(&self) -> &[Symbol] { self.as_slice() }
+}
+
+impl Debug for Annotations {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result { Debug::fmt(self.as_slice(), f) }
+}
+
+impl<S: Into<Symbol>> FromIterator<S> for Annotations {
+    fn from_iter<I: IntoIterator<Item = S>>(iter: I) -> Self {
+        let list: Vec<Symbol> = iter.into_iter().map(Into::into).collect();
+        if list.is_empty() { Annotations(None) } else { Annotations(Some(Box::new(list))) }
+    }
+}
+
+impl<S: Into<Symbol>> From<Vec<S>> for Annotations {
+    fn from(list: Vec<S>) -> Self { list.into_iter().collect() }
+}
+
+impl<'a> IntoIterator for &'a Annotations {
+    type Item = &'a Symbol;
+    type IntoIter = std::slice::Iter<'a, Symbol>;
+    fn into_iter(self) -> Self::IntoIter { self.as_slice().iter() }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::mem::size_of;
+    use super::*;
+    use crate::reader::IonReader;
+    use crate::types::IonValue;
+
+    #[test]
+    fn values_stay_small() {
+        assert_eq!(size_of::<Annotations>(), size_of::<usize>());
+        assert_eq!(size_of::<Symbol>(), size_of::<[usize; 2]>());
+        // the largest variant is `Struct`'s map, so annotations don't add to the size
+        assert!(size_of::<IonValue>() <= size_of::<[usize; 8]>(), "IonValue is {} bytes", size_of::<IonValue>());
+    }
+
+    #[test]
+    fn shares_text_within_a_document() {
+        let doc = IonReader::read_string("tag::{ name: a } tag::{ name: b } [a, name, tag]").unwrap();
+        let names: Vec<&Symbol> = doc.iter().take(2).map(|v| v.as_struct().unwrap().iter_fields().next().unwrap().0).collect();
+        assert!(Arc::ptr_eq(&names[0].0, &names[1].0));
+        assert!(Arc::ptr_eq(&doc.values()[0].annotations()[0].0, &doc.values()[1].annotations()[0].0));
+        // symbol values share text with each other and with field names and annotations
+        let symbols: Vec<&Symbol> = doc.values()[2].as_list().unwrap().iter().map(|v| match v {
+            IonValue::Symbol(s, _) => s,
+            other => panic!("expected a symbol, got {:?}", other),
+        }).collect();
+        match doc.values()[0].as_struct().unwrap().field("name") {
+            Some(IonValue::Symbol(a, _)) => assert!(Arc::ptr_eq(&a.0, &symbols[0].0)),
+            other => panic!("expected a symbol, got {:?}", other),
+        }
+        assert!(Arc::ptr_eq(&symbols[1].0, &names[0].0));
+        assert!(Arc::ptr_eq(&symbols[2].0, &doc.values()[0].annotations()[0].0));
+
+        let other = IonReader::read_string("{ name: c }").unwrap();
+        let name = other.values()[0].as_struct().unwrap().iter_fields().next().unwrap().0;
+        assert_eq!(name, names[0]);
+        assert!(!Arc::ptr_eq(&name.0, &names[0].0));
+    }
+
+    #[test]
+    fn tables_are_independent() {
+        let mut first = SymbolTable::new();
+        let mut second = SymbolTable::new();
+        let a = first.intern("frames");
+        assert!(Arc::ptr_eq(&a.0, &first.intern("frames").0));
+        assert!(!Arc::ptr_eq(&a.0, &second.intern("frames").0));
+        assert_eq!(a, Symbol::new("frames"));
+    }
+}
@@ -0,0 +1,45 @@
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use crate::reader::IonReader;
+use crate::types::IonValue;
+
+/// Reads a single value from Ion text, panicking if it isn't exactly one valid value.
+pub(crate) fn value(text: &str) -> IonValue {
+    IonReader::read_string(text).unwrap().into_single().unwrap()
+}
+
+/// A directory under the system temp directory, deleted along with its contents when dropped.
+pub(crate) struct TempDir(PathBuf);
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Writes `files` into a fresh directory named after `name`, which is unique to each test.
+pub(crate) fn write_files(name: &str, files: &[(&str, &str)]) -> TempDir {
+    let dir = TempDir(std::env::temp_dir().join(format!("electrolyte-{}-{}", name, std::process::id())));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    for (file, contents) in files {
+        let path = dir.join(file);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+    dir
+}
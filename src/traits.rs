@@ -1,5 +1,7 @@
+use bytes::Bytes;
 use crate::error::IonResult;
 use crate::walker::IonWalker;
+use crate::borrowed::IonRefWalker;
 
 pub trait IonDeserialize: Sized {
     fn deserialize<'d>(walker: &IonWalker<'d>) -> IonResult<Self>;
@@ -24,4 +26,61 @@ impl IonDeserialize for i64 {
     fn deserialize(walker: &IonWalker) -> IonResult<Self> {
         walker.as_integer()
     }
-}
\ No newline at end of file
+}
+impl IonDeserialize for bool {
+    fn deserialize(walker: &IonWalker) -> IonResult<Self> {
+        walker.as_boolean()
+    }
+}
+impl IonDeserialize for String {
+    fn deserialize(walker: &IonWalker) -> IonResult<Self> {
+        Ok(walker.as_string()?.to_string())
+    }
+}
+impl IonDeserialize for Bytes {
+    fn deserialize(walker: &IonWalker) -> IonResult<Self> {
+        walker.as_bytes()
+    }
+}
+
+/// Like `IonDeserialize`, but for types which borrow from the value being read, such as a struct
+/// with `&'a str` fields. See `IonReader::read_borrowed`.
+pub trait IonDeserializeRef<'a>: Sized {
+    fn deserialize_ref(walker: &IonRefWalker<'_, 'a>) -> IonResult<Self>;
+}
+
+impl<'a> IonDeserializeRef<'a> for f32 {
+    fn deserialize_ref(walker: &IonRefWalker<'_, 'a>) -> IonResult<Self> {
+        Ok(walker.as_float()? as f32)
+    }
+}
+impl<'a> IonDeserializeRef<'a> for f64 {
+    fn deserialize_ref(walker: &IonRefWalker<'_, 'a>) -> IonResult<Self> {
+        walker.as_float()
+    }
+}
+impl<'a> IonDeserializeRef<'a> for i32 {
+    fn deserialize_ref(walker: &IonRefWalker<'_, 'a>) -> IonResult<Self> {
+        Ok(walker.as_integer()? as i32)
+    }
+}
+impl<'a> IonDeserializeRef<'a> for i64 {
+    fn deserialize_ref(walker: &IonRefWalker<'_, 'a>) -> IonResult<Self> {
+        walker.as_integer()
+    }
+}
+impl<'a> IonDeserializeRef<'a> for bool {
+    fn deserialize_ref(walker: &IonRefWalker<'_, 'a>) -> IonResult<Self> {
+        walker.as_boolean()
+    }
+}
+impl<'a> IonDeserializeRef<'a> for &'a str {
+    fn deserialize_ref(walker: &IonRefWalker<'_, 'a>) -> IonResult<Self> {
+        walker.as_string()
+    }
+}
+impl<'a> IonDeserializeRef<'a> for &'a [u8] {
+    fn deserialize_ref(walker: &IonRefWalker<'_, 'a>) -> IonResult<Self> {
+        walker.as_blob()
+    }
+}
@@ -1,9 +1,9 @@
 use std::collections::HashMap;
 use std::ops::Index;
+use bytes::Bytes;
 use chrono::{DateTime, FixedOffset};
 use crate::{IonDeserialize, IonWalker, IonResult};
-
-pub type Annotations = Vec<String>;
+pub use crate::symbol::{Annotations, Symbol};
 
 #[derive(Debug, Clone, PartialEq)]
 /// A value in an Ion data structure with any annotations
@@ -13,8 +13,11 @@ pub enum IonValue {
     Integer(i64, Annotations),
     Float(f64, Annotations),
     Timestamp(DateTime<FixedOffset>, Annotations),
-    Blob(Vec<u8>, Annotations),
+    /// Blob contents, in a buffer which clones share. This was a `Vec<u8>` before `read_bytes`
+    /// was added; use `to_vec()` or `&blob[..]` where one is needed.
+    Blob(Bytes, Annotations),
     String(String, Annotations),
+    Symbol(Symbol, Annotations),
     List(IonList, Annotations),
     Struct(IonStruct, Annotations),
 }
@@ -28,6 +31,7 @@ impl IonValue {
             IonValue::Float(_,_) => IonType::Float,
             IonValue::Timestamp(_,_) => IonType::Timestamp,
             IonValue::String(_,_) => IonType::String,
+            IonValue::Symbol(_,_) => IonType::Symbol,
             IonValue::Blob(_,_) => IonType::Blob,
             IonValue::List(_,_) => IonType::List,
             IonValue::Struct(_,_) => IonType::Struct,
@@ -80,6 +84,20 @@ impl IonValue {
         if let IonValue::String(s,_) = &self { Some(s.as_ref()) }
         else { None }
     }
+    /// Attempts to retrieve a symbol value. Returns `None` if the value is of a different type.
+    pub fn as_symbol(&self) -> Option<&str> {
+        if let IonValue::Symbol(s,_) = &self { Some(s.as_ref()) }
+        else { None }
+    }
+    /// Attempts to retrieve the text of a string or symbol value.
+    /// Returns `None` if the value is of a different type.
+    pub fn as_text(&self) -> Option<&str> {
+        match &self {
+            IonValue::String(s,_) => Some(s.as_str()),
+            IonValue::Symbol(s,_) => Some(s.as_str()),
+            _ => None
+        }
+    }
     /// Attempts to retrieve a timestamp value. Returns `None` if the value is of a different type.
     pub fn as_timestamp(&self) -> Option<&DateTime<FixedOffset>> {
         if let IonValue::Timestamp(ts,_) = &self { Some(ts) }
@@ -99,15 +117,29 @@ impl IonValue {
             IonValue::Timestamp(_,ann) => ann,
             IonValue::Blob(_,ann) => ann,
             IonValue::String(_,ann) => ann,
+            IonValue::Symbol(_,ann) => ann,
+            IonValue::List(_,ann) => ann,
+            IonValue::Struct(_,ann) => ann,
+        }
+    }
+    /// Returns a mutable reference to this value's annotations.
+    pub fn annotations_mut(&mut self) -> &mut Annotations {
+        match self {
+            IonValue::Null(ann) => ann,
+            IonValue::Boolean(_,ann) => ann,
+            IonValue::Integer(_,ann) => ann,
+            IonValue::Float(_,ann) => ann,
+            IonValue::Timestamp(_,ann) => ann,
+            IonValue::Blob(_,ann) => ann,
+            IonValue::String(_,ann) => ann,
+            IonValue::Symbol(_,ann) => ann,
             IonValue::List(_,ann) => ann,
             IonValue::Struct(_,ann) => ann,
         }
     }
     /// Returns true if the value has any annotations.
     pub fn has_annotation(&self, annotation: impl AsRef<str>) -> bool {
-        self.annotations().iter()
-                          .find(|ann| ann.as_str() == annotation.as_ref())
-                          .is_some()
+        self.annotations().contains(annotation.as_ref())
     }
 }
 
@@ -120,20 +152,21 @@ pub enum IonType {
     Float,
     Timestamp,
     String,
+    Symbol,
     Blob,
     List,
     Struct,
 }
 
 #[derive(Debug, Clone, PartialEq)]
-/// An Ion struct. Thin wrapper over a map of strings to `IonValue`s.
+/// An Ion struct. Thin wrapper over a map of interned field names to `IonValue`s.
 pub struct IonStruct {
-    fields: HashMap<String, IonValue>,
+    fields: HashMap<Symbol, IonValue>,
 }
 
 impl IonStruct {
     /// Create a new `IonStruct` from the given field map.
-    pub fn new(fields: HashMap<String, IonValue>) -> Self { IonStruct { fields } }
+    pub fn new(fields: HashMap<Symbol, IonValue>) -> Self { IonStruct { fields } }
     /// Create a new `IonStruct` with no fields.
     pub fn new_empty() -> Self { IonStruct { fields: HashMap::new() } }
 
@@ -142,8 +175,36 @@ impl IonStruct {
         self.fields.get(name)
     }
 
+    /// Attempts to retrieve a field along with the struct's own copy of its name.
+    /// Returns `None` if the field is not present.
+    pub fn field_entry(&self, name: &str) -> Option<(&Symbol, &IonValue)> {
+        self.fields.get_key_value(name)
+    }
+
+    /// Attempts to retrieve a mutable reference to the field with the given name.
+    /// Returns `None` if the field is not present.
+    pub fn field_mut(&mut self, name: &str) -> Option<&mut IonValue> {
+        self.fields.get_mut(name)
+    }
+
+    /// Sets the field with the given name, returning its previous value if there was one.
+    pub fn insert(&mut self, name: impl Into<Symbol>, value: IonValue) -> Option<IonValue> {
+        self.fields.insert(name.into(), value)
+    }
+
+    /// Removes the field with the given name, returning its value if it was present.
+    pub fn remove(&mut self, name: &str) -> Option<IonValue> {
+        self.fields.remove(name)
+    }
+
+    /// Returns the number of fields.
+    pub fn len(&self) -> usize { self.fields.len() }
+
+    /// Returns true if the struct has no fields.
+    pub fn is_empty(&self) -> bool { self.fields.is_empty() }
+
     /// Returns an iterator over the struct's fields.
-    pub fn iter_fields(&self) -> std::collections::hash_map::Iter<String, IonValue> {
+    pub fn iter_fields(&self) -> std::collections::hash_map::Iter<Symbol, IonValue> {
         self.fields.iter()
     }
 
@@ -156,7 +217,7 @@ impl IonStruct {
         let scopes = scopes.unwrap_or(Vec::new());
         for (k, v) in self.fields.iter() {
             let value = IonWalker::deserialize_with_scopes(v, &scopes)?;
-            map.insert(k.clone(), value);
+            map.insert(k.to_string(), value);
         }
         Ok(map)
     }
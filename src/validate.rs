@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::ops::{Bound, RangeBounds};
+use regex::Regex;
+use crate::error::IonResult;
+use crate::traits::IonDeserialize;
+use crate::types::IonList;
+use crate::walker::IonWalker;
+
+/// Checks invariants of a value once it has been deserialized, e.g. that an animation's loop
+/// start is within its frames. The walker is the one the value was deserialized from, so errors
+/// can point at the offending field.
+///
+/// Run with `IonWalker::validated`, `get_validated` or `deserialize_validated`. Checks on a
+/// single field are simpler to do while reading it, with `IonWalker::get_checked` and the
+/// combinators in this module:
+///
+/// ```ignore
+/// static SPRITE: OnceLock<Regex> = OnceLock::new();
+///
+/// impl IonDeserialize for Frame {
+///     fn deserialize(walker: &IonWalker) -> IonResult<Self> {
+///         Ok(Frame {
+///             duration: walker.get_checked("duration", range((Bound::Excluded(0.0), Bound::Unbounded)))?,
+///             blend: walker.get_checked("blend", one_of(&["add", "alpha"]))?,
+///             sprite: walker.get_checked("sprite", matches(SPRITE.get_or_init(|| Regex::new(r"^[a-z0-9_]+\.png$").unwrap())))?,
+///         })
+///     }
+/// }
+///
+/// impl IonValidate for Animation {
+///     fn validate(&self, walker: &IonWalker) -> IonResult<()> {
+///         walker.get_checked("loop_start", range(0..self.frames.len()))?;
+///         Ok(())
+///     }
+/// }
+/// ```
+pub trait IonValidate: IonDeserialize {
+    fn validate(&self, walker: &IonWalker) -> IonResult<()>;
+}
+
+/// Fails unless the value lies within `range`, e.g. `range(1..=60)` or `range(0.0..)`.
+pub fn range<T, R>(range: R) -> impl Fn(&T) -> Result<(), String>
+    where T: PartialOrd + Display, R: RangeBounds<T>
+{
+    move |value| {
+        if range.contains(value) { return Ok(()); }
+        if let (Bound::Included(start), Bound::Included(end)) = (range.start_bound(), range.end_bound()) {
+            return Err(format!("must be between {} and {}, found {}", start, end, value));
+        }
+        let mut bounds = Vec::new();
+        match range.start_bound() {
+            Bound::Included(start) => bounds.push(format!("at least {}", start)),
+            Bound::Excluded(start) => bounds.push(format!("greater than {}", start)),
+            Bound::Unbounded => {}
+        }
+        match range.end_bound() {
+            Bound::Included(end) => bounds.push(format!("at most {}", end)),
+            Bound::Excluded(end) => bounds.push(format!("less than {}", end)),
+            Bound::Unbounded => {}
+        }
+        Err(format!("must be {}, found {}", bounds.join(" and "), value))
+    }
+}
+
+/// Fails if the string or collection is empty.
+pub fn non_empty<T: Length + ?Sized>() -> impl Fn(&T) -> Result<(), String> {
+    |value| {
+        if value.length() > 0 { Ok(()) }
+        else { Err("must not be empty".to_string()) }
+    }
+}
+
+/// Fails unless the value equals one of `options`.
+pub fn one_of<'o, T, O>(options: &'o [O]) -> impl Fn(&T) -> Result<(), String> + 'o
+    where T: PartialEq<O> + Display + ?Sized, O: Display
+{
+    move |value| {
+        if options.iter().any(|option| value == option) { return Ok(()); }
+        let options: Vec<String> = options.iter().map(|option| option.to_string()).collect();
+        Err(format!("expected one of: {}, found `{}`", options.join(", "), value))
+    }
+}
+
+/// Fails unless `regex` matches the string; anchor it with `^` and `$` to match all of it.
+/// Build the regex once, e.g. in a `static` `OnceLock`, rather than in `deserialize`.
+pub fn matches<'r, T: AsRef<str> + ?Sized>(regex: &'r Regex) -> impl Fn(&T) -> Result<(), String> + 'r {
+    move |value: &T| {
+        if regex.is_match(value.as_ref()) { Ok(()) }
+        else { Err(format!("`{}` doesn't match /{}/", value.as_ref(), regex.as_str())) }
+    }
+}
+
+/// Anything with a length, for `non_empty`.
+pub trait Length {
+    fn length(&self) -> usize;
+}
+
+impl Length for str {
+    fn length(&self) -> usize { self.len() }
+}
+impl Length for String {
+    fn length(&self) -> usize { self.len() }
+}
+impl<T> Length for [T] {
+    fn length(&self) -> usize { self.len() }
+}
+impl<T> Length for Vec<T> {
+    fn length(&self) -> usize { self.len() }
+}
+impl<K, V> Length for HashMap<K, V> {
+    fn length(&self) -> usize { self.len() }
+}
+impl Length for IonList {
+    fn length(&self) -> usize { self.len() }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::OnceLock;
+    use rstest::rstest;
+    use super::*;
+    use crate::error::{IonError, IonErrorType};
+    use crate::test_util::value;
+
+    #[derive(Debug)]
+    struct Animation {
+        frames: Vec<String>,
+        loop_start: i64,
+    }
+
+    struct Frame {
+        sprite: String,
+    }
+
+    impl IonDeserialize for Frame {
+        fn deserialize(walker: &IonWalker) -> IonResult<Self> {
+            static SPRITE: OnceLock<Regex> = OnceLock::new();
+            let sprite = SPRITE.get_or_init(|| Regex::new(r"^[a-z0-9_]+\.png$").unwrap());
+            Ok(Frame { sprite: walker.get_checked("sprite", matches(sprite))? })
+        }
+    }
+
+    impl IonDeserialize for Animation {
+        fn deserialize(walker: &IonWalker) -> IonResult<Self> {
+            Ok(Animation {
+                frames: walker.get_typed_list::<Frame>("frames")?.into_iter().map(|frame| frame.sprite).collect(),
+                loop_start: walker.get_checked("loop_start", range(0..))?,
+            })
+        }
+    }
+
+    impl IonValidate for Animation {
+        fn validate(&self, walker: &IonWalker) -> IonResult<()> {
+            walker.get_checked("loop_start", range(0..self.frames.len() as i64))?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn accepts_valid_values() {
+        let doc = value(r#"{ frames: [{ sprite: "walk_0.png" }, { sprite: "walk_1.png" }], loop_start: 1 }"#);
+        let anim: Animation = IonWalker::deserialize_validated(&doc).unwrap();
+        assert_eq!(anim.frames, vec!["walk_0.png", "walk_1.png"]);
+        assert_eq!(anim.loop_start, 1);
+    }
+
+    #[rstest(text, scopes, message,
+        case(r#"{ frames: [{ sprite: "walk_0.png" }, { sprite: "Walk.png" }], loop_start: 0 }"#,
+             vec!["frames", "[1]", "sprite"], "`Walk.png` doesn't match /^[a-z0-9_]+\\.png$/"),
+        case(r#"{ frames: [], loop_start: -1 }"#, vec!["loop_start"], "must be at least 0, found -1"),
+        case(r#"{ frames: [{ sprite: "a.png" }], loop_start: 1 }"#, vec!["loop_start"], "must be at least 0 and less than 1, found 1"),
+    )]
+    fn reports_where_checks_failed(text: &str, scopes: Vec<&str>, message: &str) {
+        match IonWalker::deserialize_validated::<Animation>(&value(text)) {
+            Err(IonError { ty: IonErrorType::Validation(msg), scopes: found }) => {
+                assert_eq!(found, scopes);
+                assert_eq!(msg, message);
+            }
+            other => panic!("expected a validation error, got {:?}", other),
+        }
+    }
+
+    #[rstest(check, ok,
+        case(range(1..=60)(&61), false),
+        case(range(1..=60)(&60), true),
+        case(non_empty::<str>()(""), false),
+        case(non_empty::<Vec<i64>>()(&vec![1]), true),
+        case(one_of(&["add", "alpha"])(&"add".to_string()), true),
+        case(one_of(&["add", "alpha"])(&"multiply".to_string()), false),
+    )]
+    fn combinators(check: Result<(), String>, ok: bool) {
+        assert_eq!(check.is_ok(), ok, "{:?}", check);
+    }
+
+    #[test]
+    fn matches_prebuilt_regexes() {
+        let regex = Regex::new("^[a-z]+$").unwrap();
+        let check = matches::<str>(&regex);
+        assert!(check("walk").is_ok());
+        assert_eq!(check("walk_0").unwrap_err(), "`walk_0` doesn't match /^[a-z]+$/");
+    }
+}
@@ -1,26 +1,33 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use bytes::Bytes;
 use chrono::{DateTime, FixedOffset};
 use crate::error::{IonError, IonErrorType, IonResult};
-use crate::types::{IonStruct, IonType, IonValue, IonList, Annotations};
+use crate::types::{IonStruct, IonType, IonValue, IonList, Annotations, Symbol};
 use crate::traits::IonDeserialize;
+use crate::validate::IonValidate;
+use crate::migrate::Migrations;
 use paste::paste;
 
+/// Generates `as_X` and `get_X` accessors for a walker with a `data` field holding a reference
+/// to a `$value`, and a `lookup` method returning a field of the current struct.
 macro_rules! type_fns {
-    ($ion_ty:ident, $pat:tt => $res:expr; $ret:ty) => {
+    ($value:ident: $ion_ty:ident, $pat:tt => $res:expr; $ret:ty) => {
         paste! {
             #[doc = "Attempt to read the current value as a " $ion_ty]
             pub fn [< as_ $ion_ty:lower >](&self) -> IonResult<$ret> {
-                match &self.data {
-                    IonValue::$ion_ty$pat => $res,
+                match self.data {
+                    $value::$ion_ty$pat => $res,
                     _ => Err(IonError::new(IonErrorType::WrongType { found: self.data.ty(), expected: IonType::$ion_ty }, self.clone_scopes()))
                 }
             }
 
-            #[doc = "Attempt to read the named field as a " $ion_ty ". Assumes current value is an `IonStruct`."]
+            #[doc = "Attempt to read the named field as a " $ion_ty ". Assumes current value is a struct."]
             pub fn [< get_ $ion_ty:lower >](&self, field_name: impl AsRef<str>) -> IonResult<$ret> {
-                match self.as_struct()?.field(field_name.as_ref()) {
+                match self.lookup(field_name.as_ref())?.map(|(_, val)| val) {
                     Some(val) => {
                         match val {
-                            IonValue::$ion_ty$pat => $res,
+                            $value::$ion_ty$pat => $res,
                             _ => Err(IonError::new(
                                 IonErrorType::WrongType { found: val.ty(), expected: IonType::$ion_ty },
                                 self.clone_scopes_with(field_name)
@@ -36,34 +43,236 @@ macro_rules! type_fns {
         }
     }
 }
+pub(crate) use type_fns;
 
+macro_rules! opt_fns {
+    ($ion_ty:ident; $ret:ty) => {
+        paste! {
+            #[doc = "Attempt to read the named field as a " $ion_ty ", returning `None` if it's absent or null."]
+            #[allow(clippy::needless_lifetimes)]
+            pub fn [< get_ $ion_ty:lower _opt >]<'s>(&'s self, field_name: impl AsRef<str>) -> IonResult<Option<$ret>> {
+                match self.lookup(field_name.as_ref())?.map(|(_, val)| val) {
+                    None | Some(IonValue::Null(_)) => Ok(None),
+                    Some(_) => self.[< get_ $ion_ty:lower >](field_name).map(Some),
+                }
+            }
+
+            #[doc = "Attempt to read the named field as a " $ion_ty ", returning `default` if it's absent or null."]
+            #[allow(clippy::needless_lifetimes)]
+            pub fn [< get_ $ion_ty:lower _or >]<'s>(&'s self, field_name: impl AsRef<str>, default: $ret) -> IonResult<$ret> {
+                Ok(self.[< get_ $ion_ty:lower _opt >](field_name)?.unwrap_or(default))
+            }
+
+            #[doc = "Attempt to read the named field as a " $ion_ty ", calling `default` if it's absent or null."]
+            #[allow(clippy::needless_lifetimes)]
+            pub fn [< get_ $ion_ty:lower _or_else >]<'s>(&'s self, field_name: impl AsRef<str>, default: impl FnOnce() -> $ret) -> IonResult<$ret> {
+                Ok(self.[< get_ $ion_ty:lower _opt >](field_name)?.unwrap_or_else(default))
+            }
+        }
+    };
+    ($ion_ty:ident; $ret:ty; default) => {
+        opt_fns!($ion_ty; $ret);
+
+        paste! {
+            #[doc = "Attempt to read the named field as a " $ion_ty ", returning the default value if it's absent or null."]
+            #[allow(clippy::needless_lifetimes)]
+            pub fn [< get_ $ion_ty:lower _or_default >]<'s>(&'s self, field_name: impl AsRef<str>) -> IonResult<$ret> {
+                Ok(self.[< get_ $ion_ty:lower _opt >](field_name)?.unwrap_or_default())
+            }
+        }
+    };
+}
+
+/// Named branches for `IonWalker::match_annotation` and friends, each deserializing one variant.
+pub type Branches<'b, T> = &'b [(&'static str, &'b dyn Fn(&IonWalker<'_>) -> IonResult<T>)];
+
+fn branch_names<T>(branches: Branches<'_, T>) -> String {
+    branches.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(", ")
+}
+
+fn unknown_variant<T>(name: &str, branches: Branches<'_, T>) -> IonErrorType {
+    IonErrorType::InvalidValue(format!("unknown variant `{}`, expected one of: {}", name, branch_names(branches)))
+}
+
+#[derive(Debug, Clone)]
+/// How a walker got to its value from its parent.
+pub(crate) enum Scope<'a> {
+    /// Adds no scope: the root walker, or one which doesn't record how it was reached.
+    Root,
+    /// A root walker given scopes up front.
+    Owned(Vec<String>),
+    /// A root walker given borrowed scopes up front.
+    Borrowed(&'a [String]),
+    Field(&'a str),
+    Index(usize),
+}
+
+impl Scope<'_> {
+    /// Appends this scope to `scopes`.
+    pub(crate) fn push_to(&self, scopes: &mut Vec<String>) {
+        match self {
+            Scope::Root => {}
+            Scope::Owned(owned) => scopes.extend(owned.iter().cloned()),
+            Scope::Borrowed(borrowed) => scopes.extend(borrowed.iter().cloned()),
+            Scope::Field(name) => scopes.push(name.to_string()),
+            Scope::Index(i) => scopes.push(format!("[{}]", i)),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+/// Records which struct fields were asked for while deserializing with a strict `IonWalker`, to
+/// catch typos like `durration: 0.5`.
+///
+/// Fields are only counted as read when read through a walker, so impls which inspect the
+/// `IonStruct` directly should use `iter_fields` instead. Structs which no walker looked at
+/// aren't checked.
+pub struct FieldCheck {
+    /// Field names asked for, keyed by the address of the struct they were asked of.
+    accessed: RefCell<HashMap<usize, Vec<String>>>,
+}
+
+impl FieldCheck {
+    pub fn new() -> Self { FieldCheck::default() }
+
+    fn mark(&self, st: &IonStruct, name: &str) {
+        self.accessed.borrow_mut().entry(st as *const IonStruct as usize).or_default().push(name.to_string());
+    }
+
+    /// Returns an `UnknownField` error for each field of the structs in `root` which was never
+    /// asked for, suggesting the closest name that was. `root` must be the value the strict
+    /// walker was created for.
+    pub fn unknown_fields(&self, root: &IonValue) -> Vec<IonError> {
+        let mut unknown = Vec::new();
+        self.find_unknown(root, &mut Vec::new(), &mut unknown);
+        unknown
+    }
+
+    fn find_unknown(&self, value: &IonValue, scopes: &mut Vec<String>, unknown: &mut Vec<IonError>) {
+        match value {
+            IonValue::Struct(st, _) => {
+                let mut fields: Vec<(&Symbol, &IonValue)> = st.iter_fields().collect();
+                fields.sort_by(|a, b| a.0.cmp(b.0));
+                if let Some(accessed) = self.accessed.borrow().get(&(st as *const IonStruct as usize)) {
+                    for (name, _) in fields.iter().filter(|(name, _)| !accessed.iter().any(|a| a == name.as_str())) {
+                        let suggestion = suggest(name, accessed).map(str::to_string);
+                        let mut scopes = scopes.clone();
+                        scopes.push(name.to_string());
+                        unknown.push(IonError::new(IonErrorType::UnknownField { name: name.to_string(), suggestion }, scopes));
+                    }
+                }
+                for (name, field) in fields {
+                    scopes.push(name.to_string());
+                    self.find_unknown(field, scopes, unknown);
+                    scopes.pop();
+                }
+            }
+            IonValue::List(list, _) => {
+                for (i, item) in list.iter().enumerate() {
+                    scopes.push(format!("[{}]", i));
+                    self.find_unknown(item, scopes, unknown);
+                    scopes.pop();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Walks an `IonValue` for `IonDeserialize` impls, keeping track of where it is for errors.
+///
+/// Walkers for nested values borrow their parent instead of copying its scopes, so walking is
+/// allocation-free; the scopes are only built when an `IonError` is created.
 pub struct IonWalker<'d> {
     data: &'d IonValue,
-    scopes: Vec<String>,
+    scope: Scope<'d>,
+    parent: Option<&'d IonWalker<'d>>,
+    check: Option<&'d FieldCheck>,
 }
 impl<'d> IonWalker<'d> {
     /// Construct an IonWalker around the given reference, with no scopes.
     pub fn new(data: &'d IonValue) -> Self {
-        IonWalker { data, scopes: Vec::new() }
+        IonWalker::root(data, Scope::Root)
     }
     /// Construct an IonWalker around the given reference, with the given scopes.
     pub fn with_scopes(data: &'d IonValue, scopes: Vec<String>) -> Self {
-        IonWalker { data, scopes }
+        IonWalker::root(data, Scope::Owned(scopes))
     }
 
-    /// Returns a copy of this IonWalker with an added scope
-    pub fn clone_with_scope(&self, scope: impl AsRef<str>) -> Self {
-        let mut scopes = self.scopes.clone();
-        scopes.push(scope.as_ref().to_string());
-        IonWalker { data: self.data, scopes }
+    fn root(data: &'d IonValue, scope: Scope<'d>) -> Self {
+        IonWalker { data, scope, parent: None, check: None }
+    }
+
+    /// Makes this walker (and those created from it) record the fields asked for in `check`.
+    pub fn strict(mut self, check: &'d FieldCheck) -> Self {
+        self.check = Some(check);
+        self
+    }
+
+    /// Returns a walker over the same value with an added scope, borrowing this one like `enter`.
+    pub fn clone_with_scope<'s, S: AsRef<str> + ?Sized>(&'s self, scope: &'s S) -> IonWalker<'s> {
+        self.child(self.data, Scope::Field(scope.as_ref()))
     }
 
-    /// Returns a copy of this IonWalker's scopes
-    pub fn clone_scopes(&self) -> Vec<String> { self.scopes.clone() }
+    /// Like `clone_with_scope`, but copies this walker's scopes into a new root walker instead of
+    /// borrowing it, so `scope` can be a temporary.
+    pub fn clone_with_owned_scope(&self, scope: impl AsRef<str>) -> Self {
+        IonWalker { check: self.check, ..IonWalker::root(self.data, Scope::Owned(self.clone_scopes_with(scope))) }
+    }
+
+    /// Returns a walker over `data`, a field of the current value called `name`.
+    fn field_walker<'s>(&'s self, name: &'s str, data: &'s IonValue) -> IonWalker<'s> {
+        self.child(data, Scope::Field(name))
+    }
+
+    /// Returns a walker over `data`, the element of the current value at `index`.
+    fn index_walker<'s>(&'s self, index: usize, data: &'s IonValue) -> IonWalker<'s> {
+        self.child(data, Scope::Index(index))
+    }
+
+    /// Like `enter`, but the returned walker is scoped by the field's name, so errors from
+    /// deserializing it say which field they came from.
+    fn enter_scoped(&self, field_name: impl AsRef<str>) -> IonResult<IonWalker<'_>> {
+        match self.lookup(field_name.as_ref())? {
+            Some((name, field)) => Ok(self.field_walker(name, field)),
+            None => Err(IonError::new(
+                IonErrorType::MissingField(field_name.as_ref().to_string()),
+                self.clone_scopes_with(field_name)
+            ))
+        }
+    }
+
+    fn child<'s>(&'s self, data: &'s IonValue, scope: Scope<'s>) -> IonWalker<'s> {
+        IonWalker { data, scope, parent: Some(self), check: self.check }
+    }
+
+    /// Looks up a field of the current struct, remembering that it was asked for.
+    fn lookup(&self, name: &str) -> IonResult<Option<(&Symbol, &IonValue)>> {
+        let st = self.as_struct()?;
+        if let Some(check) = self.check {
+            check.mark(st, name);
+        }
+        Ok(st.field_entry(name))
+    }
+
+    /// Appends this walker's scopes (and its parents') to `scopes`, outermost first.
+    fn collect_scopes(&self, scopes: &mut Vec<String>) {
+        if let Some(parent) = self.parent {
+            parent.collect_scopes(scopes);
+        }
+        self.scope.push_to(scopes);
+    }
+
+    /// Returns this IonWalker's scopes. Allocates, so it's intended for building errors.
+    pub fn clone_scopes(&self) -> Vec<String> {
+        let mut scopes = Vec::new();
+        self.collect_scopes(&mut scopes);
+        scopes
+    }
 
-    /// Returns a copy of this IonWalker's scopes with an extra scope added
+    /// Returns this IonWalker's scopes with an extra scope added
     pub fn clone_scopes_with(&self, scope: impl AsRef<str>) -> Vec<String> {
-        let mut scopes = self.scopes.clone();
+        let mut scopes = self.clone_scopes();
         scopes.push(scope.as_ref().to_string());
         scopes
     }
@@ -80,7 +289,32 @@ impl<'d> IonWalker<'d> {
 
     /// Convenience function for deserializing values that are IonDeserialize
     pub fn deserialize_with_scopes<T: IonDeserialize>(data: &IonValue, scopes: &Vec<String>) -> IonResult<T> {
-        T::deserialize(&mut IonWalker::with_scopes(data, scopes.clone()))
+        T::deserialize(&IonWalker::root(data, Scope::Borrowed(scopes)))
+    }
+
+    /// Brings a copy of `data` up to the current version with `migrations`, then deserializes it.
+    pub fn deserialize_migrated<T: IonDeserialize>(data: &IonValue, migrations: &Migrations) -> IonResult<T> {
+        IonWalker::deserialize(&migrations.migrate(data.clone())?)
+    }
+
+    /// Deserializes and validates `data`.
+    pub fn deserialize_validated<T: IonValidate>(data: &IonValue) -> IonResult<T> {
+        IonWalker::new(data).validated()
+    }
+
+    /// Deserializes `data`, failing with an `UnknownField` error if any struct field is never read.
+    pub fn deserialize_strict<T: IonDeserialize>(data: &IonValue) -> IonResult<T> {
+        let (value, mut unknown) = IonWalker::deserialize_checked(data)?;
+        if unknown.is_empty() { Ok(value) }
+        else { Err(unknown.remove(0)) }
+    }
+
+    /// Deserializes `data`, also returning an `UnknownField` error for each struct field which
+    /// was never read, to report as warnings.
+    pub fn deserialize_checked<T: IonDeserialize>(data: &IonValue) -> IonResult<(T, Vec<IonError>)> {
+        let check = FieldCheck::new();
+        let value = T::deserialize(&IonWalker::new(data).strict(&check))?;
+        Ok((value, check.unknown_fields(data)))
     }
 
     /// Returns the list of annotations for the current value.
@@ -93,14 +327,33 @@ impl<'d> IonWalker<'d> {
         self.data.annotations().iter().find(|a| a.as_str() == ann.as_ref()).is_some()
     }
 
-    type_fns!(Struct,   (s,_) => Ok(s);         &IonStruct);
-    type_fns!(List,     (l,_) => Ok(l);         &IonList);
-    type_fns!(Null,     (_)   => Ok(());        ());
-    type_fns!(Boolean,  (b,_) => Ok(*b);        bool);
-    type_fns!(Integer,  (i,_) => Ok(*i);        i64);
-    type_fns!(String,   (s,_) => Ok(s);         &str);
-    type_fns!(Blob,     (b,_) => Ok(&b[..]);    &[u8]);
-    type_fns!(Timestamp,(t,_) => Ok(t);         &DateTime<FixedOffset>);
+    type_fns!(IonValue: Struct,   (s,_) => Ok(s);         &IonStruct);
+    type_fns!(IonValue: List,     (l,_) => Ok(l);         &IonList);
+    type_fns!(IonValue: Null,     (_)   => Ok(());        ());
+    type_fns!(IonValue: Boolean,  (b,_) => Ok(*b);        bool);
+    type_fns!(IonValue: Integer,  (i,_) => Ok(*i);        i64);
+    type_fns!(IonValue: String,   (s,_) => Ok(s);         &str);
+    type_fns!(IonValue: Symbol,   (s,_) => Ok(s);         &str);
+    type_fns!(IonValue: Blob,     (b,_) => Ok(&b[..]);    &[u8]);
+    type_fns!(IonValue: Timestamp,(t,_) => Ok(t);         &DateTime<FixedOffset>);
+
+    opt_fns!(Struct;    &'s IonStruct);
+    opt_fns!(List;      &'s IonList);
+    opt_fns!(Boolean;   bool;       default);
+    opt_fns!(Integer;   i64;        default);
+    opt_fns!(Float;     f64;        default);
+    opt_fns!(String;    &'s str;    default);
+    opt_fns!(Symbol;    &'s str;    default);
+    opt_fns!(Blob;      &'s [u8];   default);
+    opt_fns!(Timestamp; &'s DateTime<FixedOffset>);
+
+    /// Attempt to read the current value as a Blob, sharing its buffer rather than copying it.
+    pub fn as_bytes(&self) -> IonResult<Bytes> {
+        match self.data {
+            IonValue::Blob(b, _) => Ok(b.clone()),
+            _ => Err(self.error(IonErrorType::WrongType { found: self.data.ty(), expected: IonType::Blob })),
+        }
+    }
 
     /// Attempt to read the current value as a float
     // Special case for float accepting integers
@@ -115,7 +368,7 @@ impl<'d> IonWalker<'d> {
     /// Attempt to read the named field as a float. Assumes current value is an `IonStruct`.
     // Special case for float accepting integers
     pub fn get_float(&self, field_name: impl AsRef<str>) -> IonResult<f64> {
-        match self.as_struct()?.field(field_name.as_ref()) {
+        match self.lookup(field_name.as_ref())?.map(|(_, val)| val) {
             Some(val) => {
                 match val {
                     IonValue::Float(f, _) => Ok(*f),
@@ -140,30 +393,96 @@ impl<'d> IonWalker<'d> {
 
     /// Generic version of the get_X method that works for any type which is `IonDeserialize`.
     pub fn get_type<T: IonDeserialize>(&self, field_name: impl AsRef<str>) -> IonResult<T> {
-        match self.as_struct()?.field(field_name.as_ref()) {
-            Some(field) => {
-                T::deserialize(&IonWalker {
-                    data: field,
-                    scopes: self.clone_scopes_with(field_name)
-                })
-            }
-            None => Err(IonError::new(
-                IonErrorType::MissingField(field_name.as_ref().to_string()),
-                self.clone_scopes_with(field_name)
-            ))
+        T::deserialize(&self.enter_scoped(field_name)?)
+    }
+
+    /// Deserializes the named field, returning `None` if it's absent or null. A field which is
+    /// present but can't be deserialized is still an error. Assumes current value is an `IonStruct`.
+    pub fn get_opt<T: IonDeserialize>(&self, field_name: impl AsRef<str>) -> IonResult<Option<T>> {
+        match self.lookup(field_name.as_ref())? {
+            None | Some((_, IonValue::Null(_))) => Ok(None),
+            Some((name, field)) => T::deserialize(&self.field_walker(name, field)).map(Some),
+        }
+    }
+
+    /// Deserializes the named field, returning `default` if it's absent or null.
+    pub fn get_or<T: IonDeserialize>(&self, field_name: impl AsRef<str>, default: T) -> IonResult<T> {
+        Ok(self.get_opt(field_name)?.unwrap_or(default))
+    }
+
+    /// Deserializes the named field, returning `T::default()` if it's absent or null.
+    pub fn get_or_default<T: IonDeserialize + Default>(&self, field_name: impl AsRef<str>) -> IonResult<T> {
+        Ok(self.get_opt(field_name)?.unwrap_or_default())
+    }
+
+    /// Deserializes the named field, calling `default` if it's absent or null.
+    pub fn get_or_else<T: IonDeserialize>(&self, field_name: impl AsRef<str>, default: impl FnOnce() -> T) -> IonResult<T> {
+        Ok(self.get_opt(field_name)?.unwrap_or_else(default))
+    }
+
+    /// Calls the branch named by the first of the current value's annotations which has one,
+    /// for deserializing variants tagged like `circle::{ r: 2 }`. Errors with `MissingAnnotation`
+    /// listing the branch names if none of them match.
+    ///
+    /// ```ignore
+    /// walker.match_annotation(&[
+    ///     ("circle", &|w| Ok(Shape::Circle(w.get_float("r")?))),
+    ///     ("rect", &|w| Ok(Shape::Rect(w.get_float("w")?, w.get_float("h")?))),
+    /// ])
+    /// ```
+    pub fn match_annotation<T>(&self, branches: Branches<'_, T>) -> IonResult<T> {
+        let branch = self.annotations().iter()
+            .find_map(|ann| branches.iter().find(|(name, _)| ann.as_str() == *name));
+        match branch {
+            Some((_, f)) => f(self),
+            None => Err(self.error(IonErrorType::MissingAnnotation {
+                expected: branches.iter().map(|(name, _)| name.to_string()).collect(),
+            })),
+        }
+    }
+
+    /// Calls the branch named by the string or symbol in the current struct's `field`, for
+    /// variants tagged like `{ type: circle, r: 2 }`. The branch is given the whole struct.
+    pub fn match_field<T>(&self, field: &str, branches: Branches<'_, T>) -> IonResult<T> {
+        let tag = match self.lookup(field)? {
+            Some((_, tag)) => tag,
+            None => return Err(IonError::new(IonErrorType::MissingField(field.to_string()), self.clone_scopes_with(field))),
+        };
+        let tag = match tag.as_text() {
+            Some(tag) => tag,
+            None => return Err(IonError::new(
+                IonErrorType::InvalidValue(format!("expected a variant name, found {}", tag)),
+                self.clone_scopes_with(field),
+            )),
+        };
+        match branches.iter().find(|(name, _)| *name == tag) {
+            Some((_, f)) => f(self),
+            None => Err(IonError::new(unknown_variant(tag, branches), self.clone_scopes_with(field))),
+        }
+    }
+
+    /// Calls the branch named by the only field of the current struct, for variants written
+    /// like `{ circle: { r: 2 } }`. The branch is given a walker over that field's value.
+    pub fn match_key<T>(&self, branches: Branches<'_, T>) -> IonResult<T> {
+        let st = self.as_struct()?;
+        let (name, value) = match st.iter_fields().next() {
+            Some(field) if st.len() == 1 => field,
+            _ => return Err(self.error(IonErrorType::WrongSize { found: st.len(), expected: 1 })),
+        };
+        if let Some(check) = self.check {
+            check.mark(st, name);
+        }
+        match branches.iter().find(|(branch, _)| name == *branch) {
+            Some((_, f)) => f(&self.field_walker(name, value)),
+            None => Err(self.error(unknown_variant(name, branches))),
         }
     }
 
     /// Attempt to move into the named field without assuming its type.
     /// Assumes current value is an `IonStruct`.
-    pub fn enter(&self, field_name: impl AsRef<str>) -> IonResult<IonWalker> {
-        match self.as_struct()?.field(field_name.as_ref()) {
-            Some(val) => {
-                Ok(IonWalker {
-                    data: val,
-                    scopes: self.scopes.clone()
-                })
-            }
+    pub fn enter(&self, field_name: impl AsRef<str>) -> IonResult<IonWalker<'_>> {
+        match self.lookup(field_name.as_ref())? {
+            Some((_, val)) => Ok(self.child(val, Scope::Root)),
             None => Err(IonError::new(
                 IonErrorType::MissingField(field_name.as_ref().to_string()),
                 self.clone_scopes_with(field_name)
@@ -171,11 +490,294 @@ impl<'d> IonWalker<'d> {
         }
     }
 
+    /// Returns an iterator over walkers for each item of the current list, scoped by index.
+    pub fn iter_list(&self) -> IonResult<impl Iterator<Item = IonWalker<'_>>> {
+        Ok(self.as_list()?.iter().enumerate().map(move |(i, item)| self.index_walker(i, item)))
+    }
+
+    /// Returns an iterator over the fields of the current struct and walkers for their values,
+    /// scoped by field name. Fields are visited in no particular order.
+    pub fn iter_fields(&self) -> IonResult<impl Iterator<Item = (&str, IonWalker<'_>)>> {
+        let st = self.as_struct()?;
+        Ok(st.iter_fields().map(move |(name, field)| {
+            if let Some(check) = self.check {
+                check.mark(st, name);
+            }
+            (name.as_str(), self.field_walker(name, field))
+        }))
+    }
+
+    /// Attempt to move into the item at `index` without assuming its type.
+    /// Assumes current value is an `IonList`.
+    pub fn enter_index(&self, index: usize) -> IonResult<IonWalker<'_>> {
+        let list = self.as_list()?;
+        match list.items.get(index) {
+            Some(item) => Ok(self.index_walker(index, item)),
+            None => Err(self.error(IonErrorType::IndexOutOfBounds { tried: index, bounds: (0, list.len()) })),
+        }
+    }
+
+    /// Deserializes the item at `index`. Assumes current value is an `IonList`.
+    pub fn at<T: IonDeserialize>(&self, index: usize) -> IonResult<T> {
+        T::deserialize(&self.enter_index(index)?)
+    }
+
+    /// Deserializes every item of the current list, scoped by index.
     pub fn as_typed_list<T: IonDeserialize>(&self) -> IonResult<Vec<T>> {
-        let mut result = Vec::new();
-        for item in self.as_list()?.items.iter() {
-            result.push(IonWalker::deserialize(item)?)
+        self.iter_list()?.map(|item| T::deserialize(&item)).collect()
+    }
+
+    /// Deserializes every field of the current struct into a map keyed by field name.
+    pub fn as_typed_map<T: IonDeserialize>(&self) -> IonResult<HashMap<String, T>> {
+        self.iter_fields()?.map(|(name, field)| Ok((name.to_string(), T::deserialize(&field)?))).collect()
+    }
+
+    /// Deserializes every item of the named list field. Assumes current value is an `IonStruct`.
+    pub fn get_typed_list<T: IonDeserialize>(&self, field_name: impl AsRef<str>) -> IonResult<Vec<T>> {
+        self.enter_scoped(field_name)?.as_typed_list()
+    }
+
+    /// Deserializes every field of the named struct field into a map keyed by field name.
+    /// Assumes current value is an `IonStruct`.
+    pub fn get_typed_map<T: IonDeserialize>(&self, field_name: impl AsRef<str>) -> IonResult<HashMap<String, T>> {
+        self.enter_scoped(field_name)?.as_typed_map()
+    }
+
+    /// Deserializes the current value, then validates it with this walker.
+    pub fn validated<T: IonValidate>(&self) -> IonResult<T> {
+        let value = T::deserialize(self)?;
+        value.validate(self)?;
+        Ok(value)
+    }
+
+    /// Deserializes and validates the named field. Assumes current value is an `IonStruct`.
+    pub fn get_validated<T: IonValidate>(&self, field_name: impl AsRef<str>) -> IonResult<T> {
+        self.enter_scoped(field_name)?.validated()
+    }
+
+    /// Deserializes and validates every item of the current list.
+    pub fn as_validated_list<T: IonValidate>(&self) -> IonResult<Vec<T>> {
+        self.iter_list()?.map(|item| item.validated()).collect()
+    }
+
+    /// Deserializes and validates every item of the named list field.
+    /// Assumes current value is an `IonStruct`.
+    pub fn get_validated_list<T: IonValidate>(&self, field_name: impl AsRef<str>) -> IonResult<Vec<T>> {
+        self.enter_scoped(field_name)?.as_validated_list()
+    }
+
+    /// Deserializes the current value and runs `check` against it, turning a failure into a
+    /// `Validation` error. See the combinators in `validate`.
+    pub fn checked<T: IonDeserialize>(&self, check: impl FnOnce(&T) -> Result<(), String>) -> IonResult<T> {
+        let value = T::deserialize(self)?;
+        check(&value).map_err(|msg| self.error(IonErrorType::Validation(msg)))?;
+        Ok(value)
+    }
+
+    /// Deserializes the named field and runs `check` against it, turning a failure into a
+    /// `Validation` error scoped to that field, e.g. `walker.get_checked("duration", range(0.0..))`.
+    /// Assumes current value is an `IonStruct`.
+    pub fn get_checked<T: IonDeserialize>(&self, field_name: impl AsRef<str>, check: impl FnOnce(&T) -> Result<(), String>)
+        -> IonResult<T>
+    {
+        self.enter_scoped(field_name)?.checked(check)
+    }
+}
+
+/// Returns the name in `candidates` closest to `name`, if any is close enough to be a typo.
+fn suggest<'c>(name: &str, candidates: &'c [String]) -> Option<&'c str> {
+    let max_distance = (name.chars().count() / 3).max(1);
+    candidates.iter()
+        .map(|candidate| (strsim::levenshtein(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use super::*;
+    use crate::test_util::value;
+
+    #[derive(Debug)]
+    struct Frame {
+        _duration: i64,
+    }
+
+    impl IonDeserialize for Frame {
+        fn deserialize(walker: &IonWalker) -> IonResult<Self> {
+            Ok(Frame { _duration: walker.get_integer("duration")? })
+        }
+    }
+
+    #[test]
+    fn reports_scopes() {
+        let doc = value("{ sprite: { frames: [{ duration: 1 }, { duration: x }] } }");
+        let walker = IonWalker::new(&doc);
+        let sprite = walker.enter("sprite").unwrap();
+        let frames = sprite.enter("frames").unwrap();
+        let frame = frames.enter_index(1).unwrap();
+        // `enter` doesn't add a scope, so errors read the same as before walkers were nested
+        assert_eq!(frame.get_integer("duration").unwrap_err().scopes, vec!["[1]", "duration"]);
+        assert_eq!(sprite.get_integer("speed").unwrap_err().scopes, vec!["speed"]);
+        let list = frames.as_typed_list::<Frame>().unwrap_err();
+        assert_eq!(list.scopes, vec!["[1]", "duration"]);
+        let hero = walker.clone_with_scope("hero");
+        assert_eq!(hero.get_type::<i64>("x").unwrap_err().scopes, vec!["hero", "x"]);
+        assert_eq!(hero.clone_with_scope("nested").clone_scopes(), vec!["hero", "nested"]);
+        // the scope may be a temporary which doesn't outlive the returned walker
+        let item = hero.clone_with_owned_scope(format!("[{}]", 2));
+        assert_eq!(item.clone_scopes(), vec!["hero", "[2]"]);
+    }
+
+    #[derive(Debug)]
+    struct Sprite {
+        _name: String,
+        _frames: Vec<Frame>,
+    }
+
+    impl IonDeserialize for Sprite {
+        fn deserialize(walker: &IonWalker) -> IonResult<Self> {
+            Ok(Sprite { _name: walker.get_string("name")?.to_string(), _frames: walker.get_typed_list("frames")? })
+        }
+    }
+
+    fn unknown_field(e: &IonError) -> (&str, Option<&str>) {
+        match &e.ty {
+            IonErrorType::UnknownField { name, suggestion } => (name, suggestion.as_deref()),
+            other => panic!("expected an unknown field error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn accepts_known_fields() {
+        assert!(IonWalker::deserialize_strict::<Sprite>(&value(r#"{ name: "hero", frames: [{ duration: 1 }] }"#)).is_ok());
+    }
+
+    #[rstest(text, scopes, name, suggestion,
+        case(r#"{ name: "hero", frames: [], speed: 2 }"#, vec!["speed"], "speed", None),
+        case(r#"{ name: "hero", frames: [], nam: x }"#, vec!["nam"], "nam", Some("name")),
+        // suggestions are only made within a third of the name's length
+        case(r#"{ name: "hero", frames: [], nmae: x }"#, vec!["nmae"], "nmae", None),
+        case(r#"{ name: "hero", frames: [], frmes: [] }"#, vec!["frmes"], "frmes", Some("frames")),
+        // nested structs are checked too
+        case(r#"{ name: "hero", frames: [{ duration: 1 }, { duration: 1, durration: 2 }] }"#,
+             vec!["frames", "[1]", "durration"], "durration", Some("duration")),
+    )]
+    fn rejects_unknown_fields(text: &str, scopes: Vec<&str>, name: &str, suggestion: Option<&str>) {
+        let e = IonWalker::deserialize_strict::<Sprite>(&value(text)).unwrap_err();
+        assert_eq!(e.scopes, scopes);
+        assert_eq!(unknown_field(&e), (name, suggestion));
+    }
+
+    #[test]
+    fn collects_every_unknown_field() {
+        let doc = value(r#"{ name: "hero", frames: [{ duration: 1, durr: 1 }], speed: 2, nam: x }"#);
+        let (_, unknown) = IonWalker::deserialize_checked::<Sprite>(&doc).unwrap();
+        let scopes: Vec<&Vec<String>> = unknown.iter().map(|e| &e.scopes).collect();
+        assert_eq!(scopes, vec![&vec!["nam"], &vec!["speed"], &vec!["frames", "[0]", "durr"]]);
+        assert_eq!(unknown.iter().map(unknown_field).collect::<Vec<_>>(), vec![("nam", Some("name")), ("speed", None), ("durr", None)]);
+        // errors from deserializing still fail outright
+        assert!(IonWalker::deserialize_checked::<Sprite>(&value(r#"{ name: "hero" }"#)).is_err());
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Shape {
+        Circle(i64),
+        Rect(i64, i64),
+    }
+
+    fn circle(walker: &IonWalker) -> IonResult<Shape> { Ok(Shape::Circle(walker.get_integer("r")?)) }
+    fn rect(walker: &IonWalker) -> IonResult<Shape> { Ok(Shape::Rect(walker.get_integer("w")?, walker.get_integer("h")?)) }
+    const SHAPES: Branches<'static, Shape> = &[("circle", &circle), ("rect", &rect)];
+
+    fn invalid_value(result: IonResult<Shape>) -> (String, Vec<String>) {
+        match result {
+            Err(IonError { ty: IonErrorType::InvalidValue(msg), scopes }) => (msg, scopes),
+            other => panic!("expected an invalid value error, got {:?}", other),
+        }
+    }
+
+    #[rstest(text, expected,
+        case("circle::{ r: 2 }", Shape::Circle(2)),
+        case("rect::{ w: 1, h: 3 }", Shape::Rect(1, 3)),
+        // the first annotation naming a branch is used
+        case("solid::rect::circle::{ w: 1, h: 3 }", Shape::Rect(1, 3)),
+    )]
+    fn matches_annotations(text: &str, expected: Shape) {
+        assert_eq!(IonWalker::new(&value(text)).match_annotation(SHAPES).unwrap(), expected);
+    }
+
+    #[test]
+    fn reports_unmatched_annotations() {
+        let doc = value("[circle::{ r: 2 }, solid::{ r: 2 }, circle::{ r: x }]");
+        let walker = IonWalker::new(&doc);
+        let mut shapes = walker.iter_list().unwrap().map(|item| item.match_annotation(SHAPES));
+        assert_eq!(shapes.next().unwrap().unwrap(), Shape::Circle(2));
+        match shapes.next().unwrap() {
+            Err(IonError { ty: IonErrorType::MissingAnnotation { expected }, scopes }) => {
+                assert_eq!(expected, vec!["circle", "rect"]);
+                assert_eq!(scopes, vec!["[1]"]);
+            }
+            other => panic!("expected a missing annotation error, got {:?}", other),
         }
-        Ok(result)
+        // errors from within a branch keep their scopes
+        assert_eq!(shapes.next().unwrap().unwrap_err().scopes, vec!["[2]", "r"]);
+    }
+
+    #[rstest(text, expected,
+        case("{ type: circle, r: 2 }", Shape::Circle(2)),
+        case(r#"{ type: "rect", w: 1, h: 3 }"#, Shape::Rect(1, 3)),
+    )]
+    fn matches_tag_fields(text: &str, expected: Shape) {
+        assert_eq!(IonWalker::new(&value(text)).match_field("type", SHAPES).unwrap(), expected);
+    }
+
+    #[test]
+    fn reports_unmatched_tag_fields() {
+        let unknown = value("{ type: square, w: 1 }");
+        let (msg, scopes) = invalid_value(IonWalker::new(&unknown).match_field("type", SHAPES));
+        assert_eq!(msg, "unknown variant `square`, expected one of: circle, rect");
+        assert_eq!(scopes, vec!["type"]);
+        let (msg, scopes) = invalid_value(IonWalker::new(&value("{ type: 1 }")).match_field("type", SHAPES));
+        assert!(msg.starts_with("expected a variant name"), "{}", msg);
+        assert_eq!(scopes, vec!["type"]);
+        match IonWalker::new(&value("{ r: 2 }")).match_field("type", SHAPES) {
+            Err(IonError { ty: IonErrorType::MissingField(field), scopes }) => {
+                assert_eq!(field, "type");
+                assert_eq!(scopes, vec!["type"]);
+            }
+            other => panic!("expected a missing field error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn matches_single_keys() {
+        assert_eq!(IonWalker::new(&value("{ circle: { r: 2 } }")).match_key(SHAPES).unwrap(), Shape::Circle(2));
+        // the branch is given the field's value, scoped by its name
+        let nested = value("{ rect: { w: 1, h: x } }");
+        assert_eq!(IonWalker::new(&nested).match_key(SHAPES).unwrap_err().scopes, vec!["rect", "h"]);
+        let (msg, scopes) = invalid_value(IonWalker::new(&value("{ square: { w: 1 } }")).match_key(SHAPES));
+        assert_eq!(msg, "unknown variant `square`, expected one of: circle, rect");
+        assert!(scopes.is_empty());
+        match IonWalker::new(&value("{ circle: { r: 1 }, rect: { w: 1, h: 1 } }")).match_key(SHAPES) {
+            Err(IonError { ty: IonErrorType::WrongSize { found, expected }, .. }) => assert_eq!((found, expected), (2, 1)),
+            other => panic!("expected a wrong size error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn defaults_absent_and_null_fields() {
+        let doc = value("{ speed: null.float, name: null.string, hp: 3, tags: null }");
+        let walker = IonWalker::new(&doc);
+        assert_eq!(walker.get_float_or_default("speed").unwrap(), 0.0);
+        assert_eq!(walker.get_string_or_default("name").unwrap(), "");
+        assert_eq!(walker.get_integer_or_default("hp").unwrap(), 3);
+        assert_eq!(walker.get_integer_or_default("armor").unwrap(), 0);
+        assert!(!walker.get_boolean_or_default("flying").unwrap());
+        assert_eq!(walker.get_blob_or_default("tags").unwrap(), b"");
+        assert_eq!(walker.get_list_opt("tags").unwrap(), None);
+        assert!(walker.get_string_or_default("hp").is_err());
     }
 }
@@ -0,0 +1,219 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime};
+use crate::error::{IonError, IonResult};
+use crate::reader::{IonReader, ReadOptions};
+use crate::traits::IonDeserialize;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// What a file looked like when it was last read, used to spot modifications.
+struct Stamp {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+impl Stamp {
+    /// Returns `None` if the file can't be inspected, e.g. because it was deleted.
+    fn of(path: &Path) -> Option<Stamp> {
+        let meta = std::fs::metadata(path).ok()?;
+        Some(Stamp { modified: meta.modified().ok(), len: meta.len() })
+    }
+}
+
+struct Watched<T> {
+    path: PathBuf,
+    stamp: Option<Stamp>,
+    value: Option<T>,
+}
+
+/// Reloads Ion files when they change, for editing assets while the game is running.
+///
+/// Changes are found by polling each file's modification time and size, so no platform-specific
+/// APIs are needed. Each file must hold exactly one top-level value. A file which fails to read
+/// or deserialize keeps its last good value.
+///
+/// ```ignore
+/// let mut watcher = IonWatcher::<Sprite>::new(ReadOptions::default());
+/// watcher.watch("assets/hero.sprite.ion")?;
+/// // once per frame:
+/// watcher.poll(|path, result| match result {
+///     Ok(sprite) => reload_sprite(path, sprite),
+///     Err(e) => eprintln!("{}: {}", path.display(), e),
+/// });
+/// ```
+pub struct IonWatcher<T: IonDeserialize> {
+    files: Vec<Watched<T>>,
+    options: ReadOptions,
+}
+
+impl<T: IonDeserialize> IonWatcher<T> {
+    /// Create a watcher which reads files with the given options.
+    pub fn new(options: ReadOptions) -> Self {
+        IonWatcher { files: Vec::new(), options }
+    }
+
+    /// Starts watching `path` and loads it. The file is watched even if it fails to load,
+    /// so it's picked up once it's fixed.
+    pub fn watch(&mut self, path: impl AsRef<Path>) -> IonResult<&T> {
+        let path = path.as_ref().to_path_buf();
+        let idx = match self.files.iter().position(|f| f.path == path) {
+            Some(idx) => idx,
+            None => {
+                self.files.push(Watched { path, stamp: None, value: None });
+                self.files.len() - 1
+            }
+        };
+        let file = &mut self.files[idx];
+        file.stamp = Stamp::of(&file.path);
+        let value = load(&file.path, &self.options)?;
+        Ok(&*file.value.insert(value))
+    }
+
+    /// Stops watching `path`, returning its last good value.
+    pub fn unwatch(&mut self, path: impl AsRef<Path>) -> Option<T> {
+        let idx = self.files.iter().position(|f| f.path == path.as_ref())?;
+        self.files.remove(idx).value
+    }
+
+    /// Returns the last good value loaded from `path`.
+    pub fn get(&self, path: impl AsRef<Path>) -> Option<&T> {
+        self.files.iter().find(|f| f.path == path.as_ref()).and_then(|f| f.value.as_ref())
+    }
+
+    /// Returns the paths being watched.
+    pub fn paths(&self) -> impl Iterator<Item = &Path> {
+        self.files.iter().map(|f| f.path.as_path())
+    }
+
+    /// Reloads every file which changed since it was last read, calling `callback` with the new
+    /// value or the error for each one. Returns the number of files which changed.
+    pub fn poll(&mut self, mut callback: impl FnMut(&Path, Result<&T, &IonError>)) -> usize {
+        self.poll_owned(|path, result| match result {
+            Ok(value) => callback(path, Ok(value)),
+            Err(e) => callback(path, Err(&e)),
+        })
+    }
+
+    fn poll_owned(&mut self, mut callback: impl FnMut(&Path, Result<&T, IonError>)) -> usize {
+        let mut changed = 0;
+        for file in self.files.iter_mut() {
+            let stamp = Stamp::of(&file.path);
+            if stamp == file.stamp { continue; }
+            file.stamp = stamp;
+            changed += 1;
+            match load(&file.path, &self.options) {
+                Ok(value) => callback(&file.path, Ok(&*file.value.insert(value))),
+                Err(e) => callback(&file.path, Err(e)),
+            }
+        }
+        changed
+    }
+}
+
+impl<T: IonDeserialize + Clone + Send + 'static> IonWatcher<T> {
+    /// Moves the watcher to a background thread which polls every `interval` and sends each
+    /// reload to `sender`. The thread stops when the returned handle's `stop` is called, or when
+    /// the receiver is dropped and a reload fails to send.
+    pub fn spawn(mut self, interval: Duration, sender: Sender<(PathBuf, IonResult<T>)>) -> WatchHandle {
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = stop.clone();
+        let thread = std::thread::spawn(move || {
+            while !stopped.load(Ordering::Acquire) {
+                let mut disconnected = false;
+                self.poll_owned(|path, result| {
+                    disconnected |= sender.send((path.to_path_buf(), result.cloned())).is_err();
+                });
+                if disconnected { return; }
+                std::thread::park_timeout(interval);
+            }
+        });
+        WatchHandle { stop, thread }
+    }
+}
+
+/// Controls the background thread started by `IonWatcher::spawn`. Dropping the handle leaves the
+/// thread running.
+pub struct WatchHandle {
+    stop: Arc<AtomicBool>,
+    thread: JoinHandle<()>,
+}
+
+impl WatchHandle {
+    /// Stops the watcher thread, waking it if it's waiting for the next poll, and waits for it
+    /// to finish.
+    pub fn stop(self) {
+        self.stop.store(true, Ordering::Release);
+        self.thread.thread().unpark();
+        let _ = self.thread.join();
+    }
+
+    /// Returns true if the watcher thread has stopped, e.g. because the receiver was dropped.
+    pub fn is_finished(&self) -> bool { self.thread.is_finished() }
+}
+
+/// Reads `path` and deserializes its only top-level value.
+fn load<T: IonDeserialize>(path: &Path, options: &ReadOptions) -> IonResult<T> {
+    IonReader::read_file_with(path, options)?.deserialize()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::channel;
+    use std::time::Instant;
+    use super::*;
+    use crate::test_util::write_files;
+
+    #[test]
+    fn keeps_the_last_good_value_after_a_bad_edit() {
+        let dir = write_files("watch-poll", &[("hero.ion", "1")]);
+        let path = dir.join("hero.ion");
+        let mut watcher = IonWatcher::<i64>::new(ReadOptions::default());
+        assert_eq!(*watcher.watch(&path).unwrap(), 1);
+        assert_eq!(watcher.poll(|_, _| panic!("nothing changed")), 0);
+
+        // each edit changes the file's length, so it's seen even if the modification time isn't
+        std::fs::write(&path, "{ bad").unwrap();
+        let mut errors = Vec::new();
+        assert_eq!(watcher.poll(|changed, result| errors.push((changed.to_path_buf(), result.unwrap_err().to_string()))), 1);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].0, path);
+        assert_eq!(watcher.get(&path), Some(&1));
+
+        std::fs::write(&path, "42").unwrap();
+        let mut reloaded = Vec::new();
+        assert_eq!(watcher.poll(|_, result| reloaded.push(*result.unwrap())), 1);
+        assert_eq!(reloaded, vec![42]);
+        assert_eq!(watcher.get(&path), Some(&42));
+        assert_eq!(watcher.unwatch(&path), Some(42));
+        assert_eq!(watcher.get(&path), None);
+    }
+
+    #[test]
+    fn spawned_watcher_reloads_and_stops() {
+        let dir = write_files("watch-spawn", &[("hero.ion", "1")]);
+        let path = dir.join("hero.ion");
+        let mut watcher = IonWatcher::<i64>::new(ReadOptions::default());
+        assert_eq!(*watcher.watch(&path).unwrap(), 1);
+
+        let (sender, receiver) = channel();
+        let handle = watcher.spawn(Duration::from_millis(10), sender);
+        std::fs::write(&path, "1234").unwrap();
+        let (reloaded, value) = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(reloaded, path);
+        assert_eq!(value.unwrap(), 1234);
+        assert!(!handle.is_finished());
+        handle.stop();
+    }
+
+    #[test]
+    fn stop_wakes_a_waiting_watcher() {
+        let (sender, _receiver) = channel();
+        let handle = IonWatcher::<i64>::new(ReadOptions::default()).spawn(Duration::from_secs(600), sender);
+        let start = Instant::now();
+        handle.stop();
+        assert!(start.elapsed() < Duration::from_secs(60));
+    }
+}
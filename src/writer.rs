@@ -0,0 +1,157 @@
+use std::path::Path;
+use chrono::Timelike;
+use ion_c_sys::*;
+use ion_c_sys::result::IonCResult;
+use ion_c_sys::timestamp::{IonDateTime, Mantissa, TSOffsetKind, TSPrecision};
+use ion_c_sys::writer::{IonCValueWriter, IonCWriter, IonCWriterHandle};
+use crate::document::IonDocument;
+use crate::error::IonResult;
+use crate::printer::{PrettyConfig, PrettyPrinter};
+use crate::types::IonValue;
+
+/// Size of the first output buffer tried when writing. Doubled until the output fits.
+const INITIAL_BUFFER_SIZE: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// Output encoding for `IonWriter`.
+pub enum IonFormat {
+    Text,
+    Binary,
+}
+
+pub struct IonWriter;
+impl IonWriter {
+    /// Writes the given values to a file as top-level values in the given format.
+    /// Text is written with the default `PrettyConfig`.
+    pub fn write_file(path: impl AsRef<Path>, values: &[IonValue], format: IonFormat) -> IonResult<()> {
+        let bytes = match format {
+            IonFormat::Text => IonWriter::write_text(values).into_bytes(),
+            IonFormat::Binary => IonWriter::write_binary(values)?,
+        };
+        std::fs::write(path.as_ref(), bytes)?;
+        Ok(())
+    }
+
+    /// Writes the given values as pretty-printed Ion text, one top-level value after another.
+    ///
+    /// Use `write_document` to write back an `IonDocument` returned by `IonReader`.
+    pub fn write_text(values: &[IonValue]) -> String {
+        IonWriter::write_text_with(values, &PrettyConfig::default())
+    }
+
+    /// Writes a document's values as top-level values in the given format, the inverse of
+    /// reading it with `IonReader`. Text is written with the default `PrettyConfig`.
+    pub fn write_document(doc: &IonDocument, format: IonFormat) -> IonResult<Vec<u8>> {
+        match format {
+            IonFormat::Text => Ok(IonWriter::write_text(doc.values()).into_bytes()),
+            IonFormat::Binary => IonWriter::write_binary(doc.values()),
+        }
+    }
+
+    /// Writes the given values as Ion text laid out according to `config`.
+    pub fn write_text_with(values: &[IonValue], config: &PrettyConfig) -> String {
+        PrettyPrinter::new(config.clone()).print_all(values)
+    }
+
+    /// Writes the given values as binary Ion, one top-level value after another.
+    pub fn write_binary(values: &[IonValue]) -> IonResult<Vec<u8>> {
+        // Ion C can only write into a fixed-size buffer, so retry with a bigger one until it fits
+        let mut capacity = INITIAL_BUFFER_SIZE;
+        loop {
+            let mut buf = vec![0; capacity];
+            let result = IonWriter::write_into(&mut buf, values);
+            match result {
+                Ok(len) => {
+                    buf.truncate(len);
+                    return Ok(buf);
+                }
+                Err(e) if e.code == ion_error_code_IERR_BUFFER_TOO_SMALL => capacity *= 2,
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    fn write_into(buf: &mut [u8], values: &[IonValue]) -> IonCResult<usize> {
+        let mut options = ION_WRITER_OPTIONS {
+            output_as_binary: 1,
+            ..Default::default()
+        };
+        let mut writer = IonCWriterHandle::new_buf(buf, &mut options)?;
+        for value in values {
+            IonWriter::write_value(&mut writer, None, value)?;
+        }
+        writer.finish()
+    }
+
+    fn write_value(writer: &mut IonCWriterHandle, field: Option<&str>, value: &IonValue) -> IonCResult<()> {
+        let annotations: Vec<&str> = value.annotations().iter().map(|a| a.as_str()).collect();
+        {
+            let mut ctx = writer.annotations(&annotations);
+            if let Some(field) = field {
+                ctx.field(field);
+            }
+            match value {
+                IonValue::Null(_) => ctx.write_null(ION_TYPE_NULL)?,
+                IonValue::Boolean(b, _) => ctx.write_bool(*b)?,
+                IonValue::Integer(i, _) => ctx.write_i64(*i)?,
+                IonValue::Float(f, _) => ctx.write_f64(*f)?,
+                IonValue::Timestamp(ts, _) => {
+                    let precision = match ts.nanosecond() {
+                        0 => TSPrecision::Second,
+                        _ => TSPrecision::Fractional(Mantissa::Digits(9)),
+                    };
+                    ctx.write_datetime(&IonDateTime::try_new(*ts, precision, TSOffsetKind::KnownOffset)?)?
+                }
+                IonValue::Blob(b, _) => ctx.write_blob(b)?,
+                IonValue::String(s, _) => ctx.write_string(s)?,
+                IonValue::Symbol(s, _) => ctx.write_symbol(s)?,
+                IonValue::List(_, _) => ctx.start_container(ION_TYPE_LIST)?,
+                IonValue::Struct(_, _) => ctx.start_container(ION_TYPE_STRUCT)?,
+            }
+        }
+        match value {
+            IonValue::List(list, _) => {
+                for item in list.iter() {
+                    IonWriter::write_value(writer, None, item)?;
+                }
+                writer.finish_container()
+            }
+            IonValue::Struct(st, _) => {
+                for (name, field_value) in st.iter_fields() {
+                    IonWriter::write_value(writer, Some(name), field_value)?;
+                }
+                writer.finish_container()
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reader::IonReader;
+
+    const DOCUMENT: &str = r#"
+        $ion_1_0
+        sprite::{ name: "hero", frames: [1, 2.5e0, walk], enabled: true, data: {{aGVybw==}}, alt: null.string }
+        "second"
+        third::[]
+    "#;
+
+    #[test]
+    fn round_trips_binary() {
+        let doc = IonReader::read_string(DOCUMENT).unwrap();
+        let bytes = IonWriter::write_binary(doc.values()).unwrap();
+        assert_eq!(&bytes[..4], &[0xE0, 0x01, 0x00, 0xEA]);
+        assert_eq!(IonReader::read_buffer(&bytes).unwrap(), doc);
+    }
+
+    #[test]
+    fn round_trips_documents_as_text() {
+        let doc = IonReader::read_string(DOCUMENT).unwrap();
+        let bytes = IonWriter::write_document(&doc, IonFormat::Text).unwrap();
+        let text = String::from_utf8(bytes).unwrap();
+        assert_eq!(IonReader::read_string(&text).unwrap(), doc);
+    }
+}